@@ -2,6 +2,8 @@ use winit::keyboard::ModifiersState;
 
 pub trait ActionKey {
     fn action_key(&self) -> bool;
+    /// The modifier that makes cursor movement and deletion operate on words.
+    fn word_key(&self) -> bool;
 }
 
 impl ActionKey for ModifiersState {
@@ -14,4 +16,14 @@ impl ActionKey for ModifiersState {
     fn action_key(&self) -> bool {
         self.super_key()
     }
+
+    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+    fn word_key(&self) -> bool {
+        self.control_key()
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    fn word_key(&self) -> bool {
+        self.alt_key()
+    }
 }
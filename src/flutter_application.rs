@@ -8,28 +8,38 @@ use std::{
     },
     path::{Path, PathBuf},
     ptr::{null, null_mut},
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, OnceLock},
     thread::ThreadId,
     time::Duration,
 };
 
-use arboard::Clipboard;
 use ash::vk::Handle;
 use log::Level;
+use serde::Serialize;
 use tokio::runtime::Runtime;
 use wgpu::{Device, Instance, Queue, Surface};
 use wgpu_hal::api::Vulkan;
 use winit::{
-    dpi::PhysicalPosition,
-    event::{DeviceId, ElementState, KeyEvent, MouseButton, MouseScrollDelta, TouchPhase},
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{
+        DeviceId, ElementState, Ime, KeyEvent, MouseButton, MouseScrollDelta, Touch, TouchPhase,
+    },
     event_loop::EventLoopProxy,
     keyboard::ModifiersState,
-    window::{CursorIcon, Window},
+    window::{CursorIcon, Theme, Window},
 };
 
 use crate::{
-    flutter_application::{mouse_cursor::MouseCursor, platform::Platform, text_input::TextInput},
+    flutter_application::{
+        mouse_cursor::MouseCursor,
+        platform::{Platform, PlatformMessage, Reveal, SystemChrome, STICKY_REVEAL_DURATION},
+        text_input::TextInput,
+    },
     flutter_bindings::{
+        FlutterAccessibilityFeature_kFlutterAccessibilityFeatureBoldText,
+        FlutterAccessibilityFeature_kFlutterAccessibilityFeatureDisableAnimations,
+        FlutterAccessibilityFeature_kFlutterAccessibilityFeatureHighContrast,
+        FlutterAccessibilityFeature_kFlutterAccessibilityFeatureReduceMotion,
         FlutterCustomTaskRunners, FlutterEngine, FlutterEngineAOTData, FlutterEngineCollectAOTData,
         FlutterEngineGetCurrentTime, FlutterEngineInitialize, FlutterEngineOnVsync,
         FlutterEngineResult, FlutterEngineResult_kInternalInconsistency,
@@ -37,13 +47,20 @@ use crate::{
         FlutterEngineResult_kSuccess, FlutterEngineRunInitialized, FlutterEngineRunTask,
         FlutterEngineScheduleFrame, FlutterEngineSendPlatformMessage,
         FlutterEngineSendPlatformMessageResponse, FlutterEngineSendPointerEvent,
-        FlutterEngineSendWindowMetricsEvent, FlutterEngineShutdown, FlutterFrameInfo,
-        FlutterPlatformMessage, FlutterPlatformMessageResponseHandle,
-        FlutterPointerDeviceKind_kFlutterPointerDeviceKindMouse, FlutterPointerEvent,
-        FlutterPointerPhase, FlutterPointerPhase_kAdd, FlutterPointerPhase_kDown,
-        FlutterPointerPhase_kHover, FlutterPointerPhase_kMove, FlutterPointerPhase_kRemove,
-        FlutterPointerPhase_kUp, FlutterPointerSignalKind_kFlutterPointerSignalKindNone,
-        FlutterPointerSignalKind_kFlutterPointerSignalKindScroll, FlutterProjectArgs,
+        FlutterEngineSendWindowMetricsEvent, FlutterEngineShutdown,
+        FlutterEngineUpdateAccessibilityFeatures, FlutterFrameInfo, FlutterPlatformMessage,
+        FlutterPlatformMessageResponseHandle, FlutterPointerDeviceKind,
+        FlutterPointerDeviceKind_kFlutterPointerDeviceKindMouse,
+        FlutterPointerDeviceKind_kFlutterPointerDeviceKindTouch,
+        FlutterPointerDeviceKind_kFlutterPointerDeviceKindTrackpad, FlutterPointerEvent,
+        FlutterPointerMouseButtons_kFlutterPointerButtonMousePrimary, FlutterPointerPhase,
+        FlutterPointerPhase_kAdd, FlutterPointerPhase_kCancel, FlutterPointerPhase_kDown,
+        FlutterPointerPhase_kHover, FlutterPointerPhase_kMove, FlutterPointerPhase_kPanZoomEnd,
+        FlutterPointerPhase_kPanZoomStart, FlutterPointerPhase_kPanZoomUpdate,
+        FlutterPointerPhase_kRemove, FlutterPointerPhase_kUp,
+        FlutterPointerSignalKind_kFlutterPointerSignalKindNone,
+        FlutterPointerSignalKind_kFlutterPointerSignalKindScroll,
+        FlutterPointerSignalKind_kFlutterPointerSignalKindScrollInertiaCancel, FlutterProjectArgs,
         FlutterRendererConfig, FlutterRendererConfig__bindgen_ty_1, FlutterRendererType_kVulkan,
         FlutterSemanticsCustomAction, FlutterSemanticsNode, FlutterTask,
         FlutterTaskRunnerDescription, FlutterVulkanImage, FlutterVulkanInstanceHandle,
@@ -53,37 +70,131 @@ use crate::{
 };
 
 use self::{
-    keyboard::Keyboard, lifecycle::LifecycleState, platform_views::PlatformViewsHandler,
+    clipboard::Clipboard,
+    display::Displays,
+    drop_target::{DropEvent, DropTarget, DropTargetMessage},
+    feedback::{HapticFeedbackType, NullSoundBackend, SoundBackend},
+    keyboard::Keyboard,
+    lifecycle::{Lifecycle, LifecycleState},
+    locale::Locale,
+    orientation::Rotation,
+    platform_views::PlatformViewsHandler,
+    settings::{PlatformBrightness, Settings},
     task_runner::TaskRunner,
+    window_manager::{Position, Size, WindowManager, WindowManagerMessage},
 };
 
-// mod keyboard_event;
-// use keyboard_event::{FlutterKeyboardEvent, FlutterKeyboardEventType, LinuxToolkit};
+pub mod clipboard;
 mod compositor;
+mod display;
+mod drop_target;
+pub mod feedback;
 mod keyboard;
+mod keyboard_event;
 mod lifecycle;
+mod locale;
 mod message_codec;
 mod mouse_cursor;
+mod orientation;
 mod platform;
 mod platform_views;
+mod settings;
 mod task_runner;
+mod text_editing;
 mod text_input;
+mod undo_manager;
+mod window_manager;
 
-use compositor::Compositor;
+use compositor::{Compositor, RotatedPresenter};
 
 const PIXELS_PER_LINE: f64 = 10.0;
 const FLUTTER_TEXTINPUT_CHANNEL: &str = "flutter/textinput";
+const FLUTTER_KEYEVENT_CHANNEL: &str = "flutter/keyevent";
+const FLUTTER_UNDOMANAGER_CHANNEL: &str = "flutter/undomanager";
 const FLUTTER_MOUSECURSOR_CHANNEL: &str = "flutter/mousecursor";
 const FLUTTER_PLATFORM_CHANNEL: &str = "flutter/platform";
 const FLUTTER_LIFECYCLE_CHANNEL: &str = "flutter/lifecycle";
+const FLUTTER_SETTINGS_CHANNEL: &str = "flutter/settings";
 const FLUTTER_PLATFORM_VIEWS_CHANNEL: &str = "flutter/platform_views";
+/// Not part of Flutter, reports files dragged onto the window.
+const DROP_TARGET_CHANNEL: &str = "flutter_embedder/drop_target";
+/// Not part of Flutter, manages the window.
+const WINDOW_MANAGER_CHANNEL: &str = "flutter_embedder/window";
 
 struct PointerState {
     virtual_id: i32,
+    device_kind: FlutterPointerDeviceKind,
     position: PhysicalPosition<f64>,
     held_buttons: u64,
 }
 
+/// A trackpad gesture that contributes to a pan/zoom sequence.
+#[derive(Clone, Copy)]
+#[repr(u8)]
+enum PanZoomGesture {
+    Scroll = 1 << 0,
+    Pan = 1 << 1,
+    Pinch = 1 << 2,
+    Rotation = 1 << 3,
+}
+
+/// A pan/zoom sequence on a trackpad. winit reports pinch, pan, rotation and
+/// precise scrolling as independent gestures, while Flutter expects a single
+/// sequence with accumulated values, so it lasts as long as any of the
+/// gestures is active.
+struct PanZoomState {
+    pointer: PointerState,
+    active_gestures: u8,
+    pan: PhysicalPosition<f64>,
+    scale: f64,
+    rotation: f64,
+}
+
+/// How mouse wheel scrolling is translated for Flutter. winit doesn't expose
+/// the desktop's scroll settings, so these have to be configured by the
+/// embedder, e.g. from the command line.
+#[derive(Debug, Clone, Copy)]
+pub struct ScrollSettings {
+    /// How many logical pixels a single line of wheel scrolling moves.
+    pub pixels_per_line: f64,
+    /// Inverts the wheel's scroll direction. Trackpad deltas already follow
+    /// the desktop's natural scrolling preference.
+    pub natural_scrolling: bool,
+}
+
+impl Default for ScrollSettings {
+    fn default() -> Self {
+        Self {
+            pixels_per_line: PIXELS_PER_LINE,
+            natural_scrolling: false,
+        }
+    }
+}
+
+/// Preferences reported to Flutter that winit can't read from the desktop.
+#[derive(Debug, Clone, Copy)]
+pub struct UserSettings {
+    /// Scales all text, on top of the display's scale factor.
+    pub text_scale_factor: f64,
+    pub always_use_24_hour_format: bool,
+    pub high_contrast: bool,
+    /// Asks Flutter to shorten or skip animations.
+    pub reduce_motion: bool,
+    pub bold_text: bool,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            text_scale_factor: 1.0,
+            always_use_24_hour_format: false,
+            high_contrast: false,
+            reduce_motion: false,
+            bold_text: false,
+        }
+    }
+}
+
 struct SendFlutterTask(FlutterTask);
 unsafe impl Send for SendFlutterTask {}
 
@@ -102,6 +213,9 @@ struct FlutterApplicationUserData<'window> {
     queue: Queue,
     main_thread: ThreadId,
     render_task_runner: TaskRunner,
+    /// Read by the compositor on the render thread.
+    rotation: Mutex<Rotation>,
+    rotated_presenter: OnceLock<RotatedPresenter>,
 }
 
 pub struct FlutterApplication<'window> {
@@ -110,14 +224,33 @@ pub struct FlutterApplication<'window> {
     instance: Arc<Instance>,
     aot_data: Vec<FlutterEngineAOTData>,
     mice: HashMap<DeviceId, PointerState>,
-    current_mouse_id: i32,
+    touches: HashMap<u64, PointerState>,
+    pan_zooms: HashMap<DeviceId, PanZoomState>,
+    current_pointer_id: i32,
+    /// The last known cursor position, for events that don't come with one.
+    cursor_position: PhysicalPosition<f64>,
+    drop_target: DropTarget,
+    lifecycle: Lifecycle,
+    /// Whether the framework handles `System.requestAppExit`.
+    app_initialization_complete: bool,
+    system_chrome: SystemChrome,
+    scroll_settings: ScrollSettings,
+    user_settings: UserSettings,
+    /// The desktop's theme, `None` if unknown.
+    theme: Option<Theme>,
+    /// The settings last sent to the framework.
+    settings: Option<Settings>,
     runtime: Arc<Runtime>,
-    clipboard: Arc<Mutex<Clipboard>>,
+    clipboard: Clipboard,
     keyboard: Keyboard,
     window: &'window Window,
     platform_views_handler: PlatformViewsHandler,
+    window_manager: WindowManager,
+    displays: Displays,
     user_data: Box<FlutterApplicationUserData<'window>>,
     set_cursor_icon: Box<dyn Fn(Option<CursorIcon>) + 'static>,
+    sound_backend: Box<dyn SoundBackend>,
+    haptic_handler: Box<dyn Fn(HapticFeedbackType) + 'static>,
 }
 
 impl<'window> FlutterApplication<'window> {
@@ -217,7 +350,7 @@ impl<'window> FlutterApplication<'window> {
             .collect();
 
         let user_data = Box::new(FlutterApplicationUserData {
-            event_loop_proxy: Mutex::new(event_loop_proxy),
+            event_loop_proxy: Mutex::new(event_loop_proxy.clone()),
             instance: instance.clone(),
             runtime: runtime.clone(),
             device,
@@ -225,9 +358,11 @@ impl<'window> FlutterApplication<'window> {
             queue,
             main_thread: std::thread::current().id(),
             render_task_runner: TaskRunner::new("renderer".to_owned()),
+            rotation: Default::default(),
+            rotated_presenter: OnceLock::new(),
         });
 
-        let clipboard = Arc::new(Mutex::new(Clipboard::new().unwrap()));
+        let clipboard = Clipboard::new().unwrap();
 
         let mut instance = Self {
             engine: null_mut(),
@@ -235,14 +370,32 @@ impl<'window> FlutterApplication<'window> {
             instance,
             aot_data: vec![],
             mice: Default::default(),
-            current_mouse_id: 0,
+            touches: Default::default(),
+            pan_zooms: Default::default(),
+            current_pointer_id: 0,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+            drop_target: Default::default(),
+            lifecycle: Default::default(),
+            app_initialization_complete: false,
+            system_chrome: Default::default(),
+            scroll_settings: Default::default(),
+            user_settings: Default::default(),
+            theme: window.theme(),
+            settings: None,
             runtime,
-            keyboard: Keyboard::new(clipboard.clone()),
+            keyboard: Keyboard::new(clipboard.clone(), event_loop_proxy),
             clipboard,
             platform_views_handler: Default::default(),
+            window_manager: Default::default(),
+            displays: Default::default(),
             user_data,
             window,
             set_cursor_icon: Box::new(set_cursor_icon),
+            sound_backend: Box::new(NullSoundBackend),
+            // Desktops usually can't vibrate.
+            haptic_handler: Box::new(|feedback_type| {
+                log::debug!("Ignoring haptic feedback {feedback_type:?}")
+            }),
         };
 
         let flutter_compositor = instance.compositor.flutter_compositor(&instance);
@@ -290,6 +443,8 @@ impl<'window> FlutterApplication<'window> {
         args.dart_old_gen_heap_size = -1;
         args.log_message_callback = Some(Self::log_message_callback);
         args.on_pre_engine_restart_callback = Some(Self::on_pre_engine_restart_callback);
+        args.compute_platform_resolved_locale_callback =
+            Some(locale::compute_platform_resolved_locale_callback);
 
         std::fs::create_dir("cache").ok();
         args.persistent_cache_path = b"cache".as_ptr() as _;
@@ -323,7 +478,31 @@ impl<'window> FlutterApplication<'window> {
         Self::unwrap_result(unsafe { FlutterEngineRunInitialized(self.engine) });
     }
 
-    pub fn metrics_changed(&self, width: u32, height: u32, pixel_ratio: f64, x: i32, y: i32) {
+    pub fn metrics_changed(&mut self, width: u32, height: u32, pixel_ratio: f64, x: i32, y: i32) {
+        // There's no event for minimizing the window, but it's resized.
+        let minimized = self
+            .window
+            .is_minimized()
+            .unwrap_or(width == 0 || height == 0);
+        let state = self.lifecycle.set_minimized(minimized);
+        self.send_lifecycle_state(state);
+        if let Some(visible) = self
+            .system_chrome
+            .fullscreen_changed(self.window.fullscreen().is_some())
+        {
+            self.send_json_message(
+                FLUTTER_PLATFORM_CHANNEL,
+                &PlatformMessage::SystemChromeSystemUIChange((visible,)),
+            );
+        }
+        if self.displays.update(self.window) {
+            self.displays.notify_engine(self.engine);
+        }
+        let display_id = self.displays.display_id(self.window.current_monitor());
+        self.keyboard.metrics_changed(self.rotation(), self.window);
+        let PhysicalSize { width, height } = self
+            .rotation()
+            .flutter_size(PhysicalSize::new(width, height));
         self.user_data
             .event_loop_proxy
             .lock()
@@ -340,8 +519,8 @@ impl<'window> FlutterApplication<'window> {
                     physical_view_inset_right: 0.0,
                     physical_view_inset_bottom: 0.0,
                     physical_view_inset_left: 0.0,
-                    // TODO: fighure out what these do (display_id and view_id)
-                    display_id: 0,
+                    display_id,
+                    // TODO: figure out what view_id does
                     view_id: 0,
                 };
                 log::debug!("setting metrics to {metrics:?}");
@@ -355,14 +534,125 @@ impl<'window> FlutterApplication<'window> {
             .unwrap();
     }
 
+    fn rotation(&self) -> Rotation {
+        *self.user_data.rotation.lock().unwrap()
+    }
+
+    /// Rotates Flutter's output in the window, and pointer events back.
+    fn set_rotation(&mut self, rotation: Rotation) {
+        if rotation == self.rotation() {
+            return;
+        }
+        *self.user_data.rotation.lock().unwrap() = rotation;
+        let size = self.window.inner_size();
+        let position = self
+            .window
+            .inner_position()
+            .unwrap_or(PhysicalPosition { x: 0, y: 0 });
+        let pixel_ratio = self
+            .window
+            .current_monitor()
+            .map(|monitor| monitor.scale_factor())
+            .unwrap_or(1.0);
+        self.metrics_changed(size.width, size.height, pixel_ratio, position.x, position.y);
+    }
+
+    /// Converts a position in the window to Flutter's logical coordinates.
+    fn logical_flutter_position(&self, position: PhysicalPosition<f64>) -> (f64, f64) {
+        let position = self
+            .rotation()
+            .to_flutter(position, self.window.inner_size());
+        let scale_factor = self.window.scale_factor();
+        (position.x / scale_factor, position.y / scale_factor)
+    }
+
+    /// Plays `SystemSound.play` requests, no sounds are played until a
+    /// backend is set.
+    pub fn set_sound_backend(&mut self, sound_backend: impl SoundBackend + 'static) {
+        self.sound_backend = Box::new(sound_backend);
+    }
+
+    pub fn set_haptic_handler(&mut self, haptic_handler: impl Fn(HapticFeedbackType) + 'static) {
+        self.haptic_handler = Box::new(haptic_handler);
+    }
+
+    pub fn set_scroll_settings(&mut self, settings: ScrollSettings) {
+        self.scroll_settings = settings;
+    }
+
+    /// Sends the user's locales to the framework, the engine has to be
+    /// running. `locales` is a colon separated list like `de_AT:en_US` that
+    /// overrides the locales from the environment.
+    pub fn set_locales(&self, locales: Option<&str>) {
+        let locales = match locales {
+            Some(locales) => Locale::parse_list(locales),
+            None => Locale::from_environment(),
+        };
+        if locales.is_empty() {
+            return;
+        }
+        locale::update_locales(self.engine, locales);
+    }
+
+    /// Sends the settings to the framework, the engine has to be running.
+    pub fn set_user_settings(&mut self, settings: UserSettings) {
+        self.user_settings = settings;
+        let mut features = 0;
+        if settings.high_contrast {
+            features |= FlutterAccessibilityFeature_kFlutterAccessibilityFeatureHighContrast;
+        }
+        if settings.reduce_motion {
+            // `disableAnimations` is what Flutter's widgets check, reduce
+            // motion is its iOS flavor.
+            features |= FlutterAccessibilityFeature_kFlutterAccessibilityFeatureDisableAnimations
+                | FlutterAccessibilityFeature_kFlutterAccessibilityFeatureReduceMotion;
+        }
+        if settings.bold_text {
+            features |= FlutterAccessibilityFeature_kFlutterAccessibilityFeatureBoldText;
+        }
+        Self::unwrap_result(unsafe {
+            FlutterEngineUpdateAccessibilityFeatures(self.engine, features)
+        });
+        self.send_settings();
+    }
+
+    pub fn theme_changed(&mut self, theme: Theme) {
+        self.theme = Some(theme);
+        self.send_settings();
+    }
+
+    /// Sends the settings on `flutter/settings` if they changed.
+    fn send_settings(&mut self) {
+        let settings = Settings {
+            text_scale_factor: self.user_settings.text_scale_factor,
+            always_use_24_hour_format: self.user_settings.always_use_24_hour_format,
+            platform_brightness: self
+                .theme
+                .map(PlatformBrightness::from)
+                .unwrap_or(PlatformBrightness::Light),
+        };
+        if self.settings.as_ref() == Some(&settings) {
+            return;
+        }
+        self.send_json_message(FLUTTER_SETTINGS_CHANNEL, &settings);
+        self.settings = Some(settings);
+    }
+
+    /// Enables or disables exporting selected text to the PRIMARY selection
+    /// and pasting it with a middle click.
+    pub fn set_primary_selection_enabled(&mut self, enabled: bool) {
+        self.keyboard.set_primary_selection_enabled(enabled);
+    }
+
     fn get_mouse(&mut self, device_id: DeviceId) -> &mut PointerState {
         if !self.mice.contains_key(&device_id) {
-            let virtual_id = self.current_mouse_id;
-            self.current_mouse_id += 1;
+            let virtual_id = self.current_pointer_id;
+            self.current_pointer_id += 1;
             self.mice.insert(
                 device_id,
                 PointerState {
                     virtual_id,
+                    device_kind: FlutterPointerDeviceKind_kFlutterPointerDeviceKindMouse,
                     position: PhysicalPosition::new(0.0, 0.0),
                     held_buttons: 0,
                 },
@@ -372,7 +662,37 @@ impl<'window> FlutterApplication<'window> {
         self.mice.get_mut(&device_id).unwrap()
     }
 
+    /// Returns whether the press revealed the system overlays and must not be
+    /// sent to Flutter.
+    fn system_chrome_pressed(&mut self, position: (f64, f64)) -> bool {
+        match self.system_chrome.pressed(self.window, position) {
+            None => false,
+            Some(Reveal::UntilHidden) => true,
+            Some(Reveal::Transient) => {
+                let event_loop_proxy = self.user_data.event_loop_proxy.lock().unwrap().clone();
+                self.runtime.spawn(async move {
+                    tokio::time::sleep(STICKY_REVEAL_DURATION).await;
+                    let _ = event_loop_proxy.send_event(Box::new(|application| {
+                        application.system_chrome.hide_transient(application.window);
+                        false
+                    }));
+                });
+                false
+            }
+        }
+    }
+
     pub fn mouse_buttons(&mut self, device_id: DeviceId, state: ElementState, button: MouseButton) {
+        let position = self.get_mouse(device_id).position;
+        let position = self.logical_flutter_position(position);
+        if state == ElementState::Pressed
+            && (self.system_chrome_pressed(position)
+                || self
+                    .window_manager
+                    .chrome_pressed(self.window, button, position))
+        {
+            return;
+        }
         let mouse = self.get_mouse(device_id);
         let old_buttons_held = mouse.held_buttons != 0;
         let button_idx = match button {
@@ -387,6 +707,8 @@ impl<'window> FlutterApplication<'window> {
         };
         match state {
             ElementState::Pressed => mouse.held_buttons ^= button_idx,
+            // The press might have moved the window instead.
+            ElementState::Released if mouse.held_buttons & button_idx == 0 => return,
             ElementState::Released => mouse.held_buttons &= !button_idx,
         }
         let new_buttons_held = mouse.held_buttons != 0;
@@ -408,6 +730,10 @@ impl<'window> FlutterApplication<'window> {
             },
             None,
         );
+
+        if button == MouseButton::Middle && state == ElementState::Pressed {
+            self.keyboard.paste_primary_selection(self.engine, position);
+        }
     }
 
     pub fn mouse_entered(&mut self, device_id: DeviceId) {
@@ -420,6 +746,7 @@ impl<'window> FlutterApplication<'window> {
     }
 
     pub fn mouse_moved(&mut self, device_id: DeviceId, position: PhysicalPosition<f64>) {
+        self.cursor_position = position;
         let mouse = self.get_mouse(device_id);
         mouse.position = position;
         let buttons = mouse.held_buttons;
@@ -434,12 +761,22 @@ impl<'window> FlutterApplication<'window> {
         );
     }
 
-    pub fn mouse_wheel(
-        &mut self,
-        device_id: DeviceId,
-        delta: MouseScrollDelta,
-        _phase: TouchPhase,
-    ) {
+    pub fn mouse_wheel(&mut self, device_id: DeviceId, delta: MouseScrollDelta, phase: TouchPhase) {
+        if let MouseScrollDelta::PixelDelta(delta) = delta {
+            // Precise scrolling with phases comes from a trackpad, which Flutter
+            // wants to see as a pan so it can apply its own scroll physics.
+            let in_progress = self
+                .pan_zooms
+                .get(&device_id)
+                .is_some_and(|state| state.active_gestures & PanZoomGesture::Scroll as u8 != 0);
+            if phase == TouchPhase::Started || in_progress {
+                self.pan_zoom(device_id, PanZoomGesture::Scroll, phase, |state| {
+                    state.pan.x += delta.x;
+                    state.pan.y += delta.y;
+                });
+                return;
+            }
+        }
         let mouse = self.get_mouse(device_id);
         let buttons = mouse.held_buttons;
         self.send_pointer_event(
@@ -453,6 +790,183 @@ impl<'window> FlutterApplication<'window> {
         )
     }
 
+    pub fn pan_gesture(
+        &mut self,
+        device_id: DeviceId,
+        delta: PhysicalPosition<f32>,
+        phase: TouchPhase,
+    ) {
+        self.pan_zoom(device_id, PanZoomGesture::Pan, phase, |state| {
+            state.pan.x += delta.x as f64;
+            state.pan.y += delta.y as f64;
+        });
+    }
+
+    pub fn pinch_gesture(&mut self, device_id: DeviceId, delta: f64, phase: TouchPhase) {
+        self.pan_zoom(device_id, PanZoomGesture::Pinch, phase, |state| {
+            if delta.is_finite() {
+                state.scale *= 1.0 + delta;
+            }
+        });
+    }
+
+    pub fn rotation_gesture(&mut self, device_id: DeviceId, delta: f32, phase: TouchPhase) {
+        self.pan_zoom(device_id, PanZoomGesture::Rotation, phase, |state| {
+            // winit reports counterclockwise degrees, Flutter expects clockwise radians.
+            if delta.is_finite() {
+                state.rotation -= (delta as f64).to_radians();
+            }
+        });
+    }
+
+    fn pan_zoom(
+        &mut self,
+        device_id: DeviceId,
+        gesture: PanZoomGesture,
+        phase: TouchPhase,
+        update: impl FnOnce(&mut PanZoomState),
+    ) {
+        if !self.pan_zooms.contains_key(&device_id) {
+            let virtual_id = self.current_pointer_id;
+            self.current_pointer_id += 1;
+            self.pan_zooms.insert(
+                device_id,
+                PanZoomState {
+                    pointer: PointerState {
+                        virtual_id,
+                        device_kind: FlutterPointerDeviceKind_kFlutterPointerDeviceKindTrackpad,
+                        position: PhysicalPosition::new(0.0, 0.0),
+                        held_buttons: 0,
+                    },
+                    active_gestures: 0,
+                    pan: PhysicalPosition::new(0.0, 0.0),
+                    scale: 1.0,
+                    rotation: 0.0,
+                },
+            );
+        }
+        let position = self.mice.get(&device_id).map(|mouse| mouse.position);
+        let state = self.pan_zooms.get_mut(&device_id).unwrap();
+        if let Some(position) = position {
+            state.pointer.position = position;
+        }
+
+        let mut events = vec![];
+        match phase {
+            TouchPhase::Started | TouchPhase::Moved => {
+                if state.active_gestures == 0 {
+                    state.pan = PhysicalPosition::new(0.0, 0.0);
+                    state.scale = 1.0;
+                    state.rotation = 0.0;
+                    // Fingers touching the trackpad again stop any ongoing
+                    // kinetic scroll.
+                    let mut inertia_cancel =
+                        Self::pointer_event(&state.pointer, FlutterPointerPhase_kHover);
+                    inertia_cancel.signal_kind =
+                        FlutterPointerSignalKind_kFlutterPointerSignalKindScrollInertiaCancel;
+                    events.push(inertia_cancel);
+                    events.push(Self::pan_zoom_event(
+                        state,
+                        FlutterPointerPhase_kPanZoomStart,
+                    ));
+                }
+                state.active_gestures |= gesture as u8;
+                if phase == TouchPhase::Moved {
+                    update(state);
+                    events.push(Self::pan_zoom_event(
+                        state,
+                        FlutterPointerPhase_kPanZoomUpdate,
+                    ));
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if state.active_gestures & gesture as u8 == 0 {
+                    return;
+                }
+                state.active_gestures &= !(gesture as u8);
+                if state.active_gestures == 0 {
+                    events.push(Self::pan_zoom_event(state, FlutterPointerPhase_kPanZoomEnd));
+                }
+            }
+        }
+        if !events.is_empty() {
+            self.send_pointer_events(events);
+        }
+    }
+
+    fn pan_zoom_event(state: &PanZoomState, phase: FlutterPointerPhase) -> FlutterPointerEvent {
+        let mut event = Self::pointer_event(&state.pointer, phase);
+        event.pan_x = state.pan.x;
+        event.pan_y = state.pan.y;
+        event.scale = state.scale;
+        event.rotation = state.rotation;
+        event
+    }
+
+    pub fn touch(&mut self, touch: Touch) {
+        // winit has no pen events on Linux, pens arrive as touches or as the
+        // mouse. FlutterPointerEvent has no pressure or tilt fields either,
+        // so the force reported by winit can't be forwarded to the engine.
+        log::trace!(
+            "touch {} {:?} at {:?}, force {:?}",
+            touch.id,
+            touch.phase,
+            touch.location,
+            touch.force
+        );
+        let events = match touch.phase {
+            TouchPhase::Started => {
+                let mut events = vec![];
+                // The end of an earlier touch with the same id was missed,
+                // so it's cancelled rather than left behind.
+                if let Some(mut pointer) = self.touches.remove(&touch.id) {
+                    pointer.held_buttons = 0;
+                    events.push(Self::pointer_event(&pointer, FlutterPointerPhase_kCancel));
+                    events.push(Self::pointer_event(&pointer, FlutterPointerPhase_kRemove));
+                }
+                let virtual_id = self.current_pointer_id;
+                self.current_pointer_id += 1;
+                let pointer = self.touches.entry(touch.id).or_insert(PointerState {
+                    virtual_id,
+                    device_kind: FlutterPointerDeviceKind_kFlutterPointerDeviceKindTouch,
+                    position: touch.location,
+                    held_buttons: 0,
+                });
+                events.push(Self::pointer_event(pointer, FlutterPointerPhase_kAdd));
+                pointer.held_buttons =
+                    FlutterPointerMouseButtons_kFlutterPointerButtonMousePrimary as _;
+                events.push(Self::pointer_event(pointer, FlutterPointerPhase_kDown));
+                events
+            }
+            TouchPhase::Moved => {
+                let Some(pointer) = self.touches.get_mut(&touch.id) else {
+                    return;
+                };
+                pointer.position = touch.location;
+                vec![Self::pointer_event(pointer, FlutterPointerPhase_kMove)]
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let Some(mut pointer) = self.touches.remove(&touch.id) else {
+                    return;
+                };
+                pointer.position = touch.location;
+                pointer.held_buttons = 0;
+                vec![
+                    Self::pointer_event(
+                        &pointer,
+                        if touch.phase == TouchPhase::Ended {
+                            FlutterPointerPhase_kUp
+                        } else {
+                            FlutterPointerPhase_kCancel
+                        },
+                    ),
+                    Self::pointer_event(&pointer, FlutterPointerPhase_kRemove),
+                ]
+            }
+        };
+        self.send_pointer_events(events);
+    }
+
     fn send_pointer_event(
         &self,
         device_id: DeviceId,
@@ -460,56 +974,93 @@ impl<'window> FlutterApplication<'window> {
         scroll_delta: Option<MouseScrollDelta>,
     ) {
         if let Some(mouse) = self.mice.get(&device_id) {
-            let scroll_delta_px = {
-                match scroll_delta {
-                    Some(MouseScrollDelta::LineDelta(x, y)) => PhysicalPosition::new(
-                        (x as f64) * PIXELS_PER_LINE,
-                        (y as f64) * PIXELS_PER_LINE,
-                    ),
-                    Some(MouseScrollDelta::PixelDelta(pt)) => pt,
-                    None => PhysicalPosition::new(0.0, 0.0),
-                }
-            };
-            let event = FlutterPointerEvent {
-                struct_size: size_of::<FlutterPointerEvent>() as _,
-                phase,
-                timestamp: Self::current_time() as _,
-                x: mouse.position.x,
-                y: mouse.position.y,
-                device: mouse.virtual_id,
-                signal_kind: if scroll_delta.is_none() {
-                    FlutterPointerSignalKind_kFlutterPointerSignalKindNone
-                } else {
-                    FlutterPointerSignalKind_kFlutterPointerSignalKindScroll
-                },
-                scroll_delta_x: scroll_delta_px.x,
-                scroll_delta_y: scroll_delta_px.y,
-                device_kind: FlutterPointerDeviceKind_kFlutterPointerDeviceKindMouse,
-                buttons: mouse.held_buttons as _,
-                pan_x: 0.0,
-                pan_y: 0.0,
-                scale: 1.0,
-                rotation: 0.0,
-
-                // TODO: figure out view_id
-                view_id: 0,
-            };
-            self.user_data
-                .event_loop_proxy
-                .lock()
-                .unwrap()
-                .send_event(Box::new(move |application| {
-                    Self::unwrap_result(unsafe {
-                        FlutterEngineSendPointerEvent(application.engine, &event, 1)
-                    });
-                    drop(event);
-                    false
-                }))
-                .ok()
-                .unwrap();
+            let mut event = Self::pointer_event(mouse, phase);
+            if let Some(scroll_delta) = scroll_delta {
+                // winit reports how the content should move, Flutter expects
+                // the change of the scroll offset.
+                let scroll_delta_px = self.scroll_delta_px(scroll_delta);
+                event.signal_kind = FlutterPointerSignalKind_kFlutterPointerSignalKindScroll;
+                event.scroll_delta_x = -scroll_delta_px.x;
+                event.scroll_delta_y = -scroll_delta_px.y;
+            }
+            self.send_pointer_events(vec![event]);
         }
     }
 
+    /// Converts a scroll delta to physical pixels, applying the scroll
+    /// settings. Only wheel deltas are adjusted, trackpad deltas already
+    /// follow the desktop's scroll direction.
+    fn scroll_delta_px(&self, delta: MouseScrollDelta) -> PhysicalPosition<f64> {
+        let (x, y) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => (x as f64, y as f64),
+            MouseScrollDelta::PixelDelta(delta) => return delta,
+        };
+        let pixels_per_line = self.scroll_settings.pixels_per_line * self.window.scale_factor();
+        let mut delta = PhysicalPosition::new(x * pixels_per_line, y * pixels_per_line);
+        if self.scroll_settings.natural_scrolling {
+            delta.x = -delta.x;
+            delta.y = -delta.y;
+        }
+        // Shift+wheel isn't turned into horizontal scrolling here, the
+        // framework already flips the axes while Shift is held.
+        delta
+    }
+
+    fn pointer_event(pointer: &PointerState, phase: FlutterPointerPhase) -> FlutterPointerEvent {
+        FlutterPointerEvent {
+            struct_size: size_of::<FlutterPointerEvent>() as _,
+            phase,
+            timestamp: Self::current_time() as _,
+            x: pointer.position.x,
+            y: pointer.position.y,
+            device: pointer.virtual_id,
+            signal_kind: FlutterPointerSignalKind_kFlutterPointerSignalKindNone,
+            scroll_delta_x: 0.0,
+            scroll_delta_y: 0.0,
+            device_kind: pointer.device_kind,
+            buttons: pointer.held_buttons as _,
+            pan_x: 0.0,
+            pan_y: 0.0,
+            scale: 1.0,
+            rotation: 0.0,
+
+            // TODO: figure out view_id
+            view_id: 0,
+        }
+    }
+
+    fn send_pointer_events(&self, mut events: Vec<FlutterPointerEvent>) {
+        let rotation = self.rotation();
+        if rotation != Rotation::None {
+            let window_size = self.window.inner_size();
+            for event in &mut events {
+                let position =
+                    rotation.to_flutter(PhysicalPosition::new(event.x, event.y), window_size);
+                (event.x, event.y) = (position.x, position.y);
+                (event.scroll_delta_x, event.scroll_delta_y) =
+                    rotation.delta_to_flutter((event.scroll_delta_x, event.scroll_delta_y));
+                (event.pan_x, event.pan_y) = rotation.delta_to_flutter((event.pan_x, event.pan_y));
+            }
+        }
+        self.user_data
+            .event_loop_proxy
+            .lock()
+            .unwrap()
+            .send_event(Box::new(move |application| {
+                Self::unwrap_result(unsafe {
+                    FlutterEngineSendPointerEvent(
+                        application.engine,
+                        events.as_ptr(),
+                        events.len() as _,
+                    )
+                });
+                drop(events);
+                false
+            }))
+            .ok()
+            .unwrap();
+    }
+
     pub fn modifiers_changed(&mut self, state: ModifiersState) {
         self.keyboard.modifiers_changed(state);
     }
@@ -518,15 +1069,114 @@ impl<'window> FlutterApplication<'window> {
         self.keyboard.key_event(self.engine, event, synthesized);
     }
 
+    pub fn ime(&mut self, ime: Ime) {
+        self.keyboard.ime(self.engine, ime);
+    }
+
+    pub fn file_hovered(&mut self, path: PathBuf) {
+        let paths = self.drop_target.hovered(path);
+        self.send_json_message(
+            DROP_TARGET_CHANNEL,
+            &DropTargetMessage::Hover(self.drop_event(paths)),
+        );
+    }
+
+    pub fn file_dropped(&mut self, path: PathBuf) {
+        if let Some(paths) = self.drop_target.dropped(path) {
+            self.send_json_message(
+                DROP_TARGET_CHANNEL,
+                &DropTargetMessage::Drop(self.drop_event(paths)),
+            );
+        }
+    }
+
+    pub fn file_hover_cancelled(&mut self) {
+        self.drop_target.cancelled();
+        self.send_json_message(DROP_TARGET_CHANNEL, &DropTargetMessage::Leave);
+    }
+
+    fn drop_event(&self, paths: Vec<PathBuf>) -> DropEvent {
+        // winit doesn't report where files are dropped, so this relies on
+        // the cursor moving over the window during the drag.
+        let (x, y) = self.logical_flutter_position(self.cursor_position);
+        DropEvent { x, y, paths }
+    }
+
+    pub fn window_moved(&mut self, position: PhysicalPosition<i32>) {
+        let position = position.to_logical::<f64>(self.window.scale_factor());
+        self.send_json_message(
+            WINDOW_MANAGER_CHANNEL,
+            &WindowManagerMessage::Moved(Position::from(position)),
+        );
+    }
+
+    pub fn window_resized(&mut self, size: PhysicalSize<u32>) {
+        let size = size.to_logical::<f64>(self.window.scale_factor());
+        self.send_json_message(
+            WINDOW_MANAGER_CHANNEL,
+            &WindowManagerMessage::Resized(Size::from(size)),
+        );
+    }
+
+    fn send_json_message(&self, channel: &str, message: &impl Serialize) {
+        let channel = CString::new(channel).unwrap();
+        let message = serde_json::to_vec(message).unwrap();
+        Self::unwrap_result(unsafe {
+            FlutterEngineSendPlatformMessage(
+                self.engine,
+                &FlutterPlatformMessage {
+                    struct_size: size_of::<FlutterPlatformMessage>() as _,
+                    channel: channel.as_ptr(),
+                    message: message.as_ptr(),
+                    message_size: message.len() as _,
+                    response_handle: null(),
+                },
+            )
+        });
+    }
+
+    /// Asks the framework whether the application may exit, e.g. when the
+    /// window is closed. The event loop exits once it agrees.
+    pub fn request_exit(&mut self) {
+        let event_loop_proxy = self.user_data.event_loop_proxy.lock().unwrap().clone();
+        if self.app_initialization_complete {
+            Platform::request_app_exit(self.engine, event_loop_proxy);
+        } else {
+            event_loop_proxy
+                .send_event(Box::new(|_| true))
+                .ok()
+                .unwrap();
+        }
+    }
+
     pub fn focused(&mut self, focused: bool) {
+        let state = self.lifecycle.set_focused(focused);
+        self.send_lifecycle_state(state);
+    }
+
+    pub fn occluded(&mut self, occluded: bool) {
+        let state = self.lifecycle.set_occluded(occluded);
+        self.send_lifecycle_state(state);
+    }
+
+    pub fn suspended(&mut self, suspended: bool) {
+        let state = self.lifecycle.set_suspended(suspended);
+        self.send_lifecycle_state(state);
+    }
+
+    /// Tells the framework that the application is shutting down.
+    pub fn detach(&mut self) {
+        let state = self.lifecycle.detach();
+        self.send_lifecycle_state(state);
+    }
+
+    fn send_lifecycle_state(&self, state: Option<LifecycleState>) {
+        let Some(state) = state else {
+            return;
+        };
+        log::debug!("Lifecycle state changed to {state:?}");
         let channel = CString::new(FLUTTER_LIFECYCLE_CHANNEL).unwrap();
-        let lifecycle = serde_variant::to_variant_name(if focused {
-            &LifecycleState::Resumed
-        } else {
-            &LifecycleState::Inactive
-        })
-        .unwrap()
-        .as_bytes();
+        let lifecycle = serde_variant::to_variant_name(&state).unwrap().as_bytes();
         let message = FlutterPlatformMessage {
             struct_size: size_of::<FlutterPlatformMessage>() as _,
             channel: channel.as_ptr(),
@@ -555,6 +1205,11 @@ impl<'window> FlutterApplication<'window> {
     pub fn queue(&self) -> &Queue {
         &self.user_data.queue
     }
+    /// The system clipboard, for plugins exchanging data with other
+    /// applications.
+    pub fn clipboard(&self) -> &Clipboard {
+        &self.clipboard
+    }
 
     pub fn current_time() -> u64 {
         unsafe { FlutterEngineGetCurrentTime() }
@@ -579,10 +1234,16 @@ impl<'window> FlutterApplication<'window> {
                 let mut response = None;
                 if channel == FLUTTER_TEXTINPUT_CHANNEL {
                     if let Ok(text_input) = serde_json::from_slice::<TextInput>(&data) {
-                        this.keyboard.handle_textinput_message(text_input);
+                        this.keyboard.handle_textinput_message(text_input, this.window);
                     } else {
                         log::debug!("Unknown textinput message: {:?}", std::str::from_utf8(&data));
                     }
+                } else if channel == FLUTTER_UNDOMANAGER_CHANNEL {
+                    if let Ok(message) = serde_json::from_slice(&data) {
+                        this.keyboard.handle_undo_manager_message(message);
+                    } else {
+                        log::debug!("Unknown undomanager message: {:?}", std::str::from_utf8(&data));
+                    }
                 } else if channel == FLUTTER_PLATFORM_CHANNEL {
                     if let Ok(message) = serde_json::from_slice(&data) {
                         response = Platform::handle_message(this.engine, message, this);
@@ -595,6 +1256,12 @@ impl<'window> FlutterApplication<'window> {
                     } else {
                         log::error!("Invalid mousecursor event received! {data:?}");
                     }
+                } else if channel == WINDOW_MANAGER_CHANNEL {
+                    if let Ok(message) = serde_json::from_slice(&data) {
+                        response = this.window_manager.handle_message(message, this.window);
+                    } else {
+                        log::error!("Failed decoding {WINDOW_MANAGER_CHANNEL} message {:?}", String::from_utf8(data));
+                    }
                 } else if channel == FLUTTER_PLATFORM_VIEWS_CHANNEL {
                     if let Ok(message) = serde_json::from_slice(&data) {
                         log::debug!("Platform Views Message: {message:?}");
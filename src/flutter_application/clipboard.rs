@@ -0,0 +1,245 @@
+use std::{
+    borrow::Cow,
+    ffi::OsString,
+    io::Cursor,
+    os::unix::prelude::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use arboard::ImageData;
+#[cfg(all(
+    unix,
+    not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+))]
+use arboard::{GetExtLinux, LinuxClipboardKind, SetExtLinux};
+use image::{ImageFormat, RgbaImage};
+
+#[derive(Debug)]
+pub enum ClipboardError {
+    /// The clipboard is empty or doesn't hold data in the requested format.
+    ContentNotAvailable,
+    /// The requested clipboard doesn't exist on this platform.
+    Unsupported,
+    Clipboard(arboard::Error),
+    Image(image::ImageError),
+    InvalidUri(String),
+}
+
+impl From<arboard::Error> for ClipboardError {
+    fn from(err: arboard::Error) -> Self {
+        match err {
+            arboard::Error::ContentNotAvailable => Self::ContentNotAvailable,
+            arboard::Error::ClipboardNotSupported => Self::Unsupported,
+            err => Self::Clipboard(err),
+        }
+    }
+}
+
+impl From<image::ImageError> for ClipboardError {
+    fn from(err: image::ImageError) -> Self {
+        Self::Image(err)
+    }
+}
+
+impl std::fmt::Display for ClipboardError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ClipboardError::ContentNotAvailable => {
+                formatter.write_str("No clipboard content in the requested format")
+            }
+            ClipboardError::Unsupported => formatter.write_str("Clipboard not supported"),
+            ClipboardError::Clipboard(err) => err.fmt(formatter),
+            ClipboardError::Image(err) => err.fmt(formatter),
+            ClipboardError::InvalidUri(uri) => write!(formatter, "Not a file URI: {uri}"),
+        }
+    }
+}
+
+impl std::error::Error for ClipboardError {}
+
+/// An image on the clipboard, as 8 bit RGBA pixels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardImage {
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+impl ClipboardImage {
+    pub fn from_png(png: &[u8]) -> Result<Self, ClipboardError> {
+        let image = image::load_from_memory_with_format(png, ImageFormat::Png)?.into_rgba8();
+        Ok(Self {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            rgba: image.into_raw(),
+        })
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, ClipboardError> {
+        let image = RgbaImage::from_raw(self.width as u32, self.height as u32, self.rgba.clone())
+            .ok_or(ClipboardError::Clipboard(arboard::Error::ConversionFailure))?;
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        Ok(png)
+    }
+}
+
+/// The system clipboard, shared by the `flutter/platform` channel, the text
+/// editing shortcuts and any plugins that want to exchange data with other
+/// applications.
+#[derive(Clone)]
+pub struct Clipboard {
+    clipboard: Arc<Mutex<arboard::Clipboard>>,
+}
+
+impl Clipboard {
+    pub fn new() -> Result<Self, ClipboardError> {
+        Ok(Self {
+            clipboard: Arc::new(Mutex::new(arboard::Clipboard::new()?)),
+        })
+    }
+
+    pub fn text(&self) -> Result<String, ClipboardError> {
+        Ok(self.clipboard.lock().unwrap().get_text()?)
+    }
+
+    pub fn set_text(&self, text: &str) -> Result<(), ClipboardError> {
+        Ok(self.clipboard.lock().unwrap().set_text(text)?)
+    }
+
+    pub fn html(&self) -> Result<String, ClipboardError> {
+        Ok(self.clipboard.lock().unwrap().get().html()?)
+    }
+
+    /// Places HTML on the clipboard, along with `alt_text` for applications
+    /// that only accept plain text.
+    pub fn set_html(&self, html: &str, alt_text: Option<&str>) -> Result<(), ClipboardError> {
+        Ok(self.clipboard.lock().unwrap().set_html(html, alt_text)?)
+    }
+
+    pub fn image(&self) -> Result<ClipboardImage, ClipboardError> {
+        let image = self.clipboard.lock().unwrap().get_image()?;
+        Ok(ClipboardImage {
+            width: image.width,
+            height: image.height,
+            rgba: image.bytes.into_owned(),
+        })
+    }
+
+    pub fn set_image(&self, image: &ClipboardImage) -> Result<(), ClipboardError> {
+        Ok(self.clipboard.lock().unwrap().set_image(ImageData {
+            width: image.width,
+            height: image.height,
+            bytes: Cow::Borrowed(&image.rgba),
+        })?)
+    }
+
+    /// The files on the clipboard, e.g. copied in a file manager.
+    pub fn file_list(&self) -> Result<Vec<PathBuf>, ClipboardError> {
+        Ok(self.clipboard.lock().unwrap().get().file_list()?)
+    }
+
+    pub fn set_file_list(&self, paths: &[impl AsRef<Path>]) -> Result<(), ClipboardError> {
+        Ok(self.clipboard.lock().unwrap().set().file_list(paths)?)
+    }
+
+    /// Same as [`Clipboard::file_list`], as `file://` URIs.
+    pub fn uri_list(&self) -> Result<Vec<String>, ClipboardError> {
+        Ok(self
+            .file_list()?
+            .iter()
+            .map(|path| file_uri(path))
+            .collect())
+    }
+
+    /// Places the files at the given `file://` URIs on the clipboard. Other
+    /// schemes can't be represented by the system clipboard.
+    pub fn set_uri_list(&self, uris: &[impl AsRef<str>]) -> Result<(), ClipboardError> {
+        let paths = uris
+            .iter()
+            .map(|uri| {
+                file_path(uri.as_ref())
+                    .ok_or_else(|| ClipboardError::InvalidUri(uri.as_ref().to_owned()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.set_file_list(&paths)
+    }
+
+    #[cfg(all(
+        unix,
+        not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+    ))]
+    pub fn primary_selection_text(&self) -> Result<String, ClipboardError> {
+        Ok(self
+            .clipboard
+            .lock()
+            .unwrap()
+            .get()
+            .clipboard(LinuxClipboardKind::Primary)
+            .text()?)
+    }
+
+    #[cfg(all(
+        unix,
+        not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+    ))]
+    pub fn set_primary_selection_text(&self, text: &str) -> Result<(), ClipboardError> {
+        Ok(self
+            .clipboard
+            .lock()
+            .unwrap()
+            .set()
+            .clipboard(LinuxClipboardKind::Primary)
+            .text(text)?)
+    }
+
+    // Other platforms don't have a PRIMARY selection.
+    #[cfg(not(all(
+        unix,
+        not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+    )))]
+    pub fn primary_selection_text(&self) -> Result<String, ClipboardError> {
+        Err(ClipboardError::Unsupported)
+    }
+
+    #[cfg(not(all(
+        unix,
+        not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+    )))]
+    pub fn set_primary_selection_text(&self, _text: &str) -> Result<(), ClipboardError> {
+        Err(ClipboardError::Unsupported)
+    }
+}
+
+fn file_uri(path: &Path) -> String {
+    let mut uri = "file://".to_owned();
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            byte => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+fn file_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    // Skip the host, usually empty or `localhost`.
+    let path = &path[path.find('/')?..];
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    Some(PathBuf::from(OsString::from_vec(bytes)))
+}
@@ -1,11 +1,21 @@
 use ash::vk::Handle;
-use std::{cell::Cell, ffi::c_void, mem::size_of, ptr::null_mut};
+use std::{borrow::Cow, cell::Cell, ffi::c_void, mem::size_of, ptr::null_mut};
 
 use wgpu::{
-    CommandEncoderDescriptor, Extent3d, Origin3d, TexelCopyTextureInfo, Texture, TextureAspect,
-    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    util::{BufferInitDescriptor, DeviceExt},
+    vertex_attr_array, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
+    BufferAddress, BufferUsages, Color, ColorTargetState, ColorWrites, CommandEncoder,
+    CommandEncoderDescriptor, Device, Extent3d, FilterMode, FragmentState, LoadOp,
+    MultisampleState, Operations, Origin3d, PipelineLayoutDescriptor, PrimitiveState,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    Sampler, SamplerBindingType, SamplerDescriptor, ShaderModuleDescriptor, ShaderSource,
+    ShaderStages, StoreOp, TexelCopyTextureInfo, Texture, TextureAspect, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureViewDimension,
+    VertexBufferLayout, VertexState, VertexStepMode,
 };
 use wgpu_hal::api::Vulkan;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
 
 use crate::{
     flutter_application::FlutterApplication,
@@ -19,7 +29,197 @@ use crate::{
     },
 };
 
-use super::FlutterApplicationUserData;
+use super::{orientation::Rotation, FlutterApplicationUserData};
+
+const ROTATION_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>, @location(1) uv: vec2<f32>) -> VertexOutput {
+    return VertexOutput(vec4<f32>(position, 0.0, 1.0), uv);
+}
+
+@group(0) @binding(0) var layer: texture_2d<f32>;
+@group(0) @binding(1) var layer_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(layer, layer_sampler, in.uv);
+}
+"#;
+
+/// Draws the layers rotated into the window, which a texture copy can't do.
+pub(super) struct RotatedPresenter {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+}
+
+impl RotatedPresenter {
+    pub(super) fn new(device: &Device, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Rotation Shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(ROTATION_SHADER)),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Rotated Layer"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Rotated Layer"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Rotated Layer"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[VertexBufferLayout {
+                    array_stride: 4 * size_of::<f32>() as BufferAddress,
+                    step_mode: VertexStepMode::Vertex,
+                    attributes: &vertex_attr_array![0 => Float32x2, 1 => Float32x2],
+                }],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+        // Rotations by multiples of 90 degrees map pixels onto pixels.
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Rotated Layer"),
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+        }
+    }
+
+    /// Draws each layer, given as its texture and its rectangle in Flutter's
+    /// coordinates, rotated onto `target`.
+    fn present(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        target: &Texture,
+        rotation: Rotation,
+        layers: &[(&Texture, PhysicalPosition<f64>, PhysicalSize<f64>)],
+    ) {
+        let window_size = PhysicalSize::new(target.width(), target.height());
+        let to_ndc = |x: f64, y: f64| {
+            let position = rotation.to_window(PhysicalPosition::new(x, y), window_size);
+            [
+                (position.x / window_size.width as f64 * 2.0 - 1.0) as f32,
+                (1.0 - position.y / window_size.height as f64 * 2.0) as f32,
+            ]
+        };
+        let mut vertices = Vec::<f32>::with_capacity(layers.len() * 6 * 4);
+        for (_, offset, size) in layers {
+            let (left, top) = (offset.x, offset.y);
+            let (right, bottom) = (offset.x + size.width, offset.y + size.height);
+            for (x, y, u, v) in [
+                (left, top, 0.0, 0.0),
+                (right, top, 1.0, 0.0),
+                (left, bottom, 0.0, 1.0),
+                (left, bottom, 0.0, 1.0),
+                (right, top, 1.0, 0.0),
+                (right, bottom, 1.0, 1.0),
+            ] {
+                vertices.extend(to_ndc(x, y));
+                vertices.extend([u, v]);
+            }
+        }
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Rotated Layers"),
+            contents: &vertices
+                .iter()
+                .flat_map(|value| value.to_ne_bytes())
+                .collect::<Vec<_>>(),
+            usage: BufferUsages::VERTEX,
+        });
+        let bind_groups = layers
+            .iter()
+            .map(|(texture, _, _)| {
+                let view = texture.create_view(&Default::default());
+                device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("Rotated Layer"),
+                    layout: &self.bind_group_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(&view),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::Sampler(&self.sampler),
+                        },
+                    ],
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let view = target.create_view(&Default::default());
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Rotated Layers"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        for (idx, bind_group) in bind_groups.iter().enumerate() {
+            let first_vertex = idx as u32 * 6;
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(first_vertex..first_vertex + 6, 0..1);
+        }
+    }
+}
 
 pub struct Compositor {
     platform_view_count: Cell<i64>,
@@ -119,6 +319,8 @@ impl Compositor {
         let mut encoder = application_user_data
             .device
             .create_command_encoder(&CommandEncoderDescriptor { label: None });
+        let rotation = *application_user_data.rotation.lock().unwrap();
+        let mut rotated_layers = vec![];
         {
             // encoder.clear_texture(&frame.texture, &ImageSubresourceRange::default());
             // encoder.begin_render_pass(&RenderPassDescriptor {
@@ -157,6 +359,14 @@ impl Compositor {
                         let backing_store = unsafe { &backing_store.__bindgen_anon_1.vulkan };
                         let texture = unsafe { &*(backing_store.user_data as *mut Texture) };
 
+                        if rotation != Rotation::None {
+                            rotated_layers.push((
+                                texture,
+                                PhysicalPosition::new(offset.x, offset.y),
+                                PhysicalSize::new(size.width, size.height),
+                            ));
+                            continue;
+                        }
                         encoder.copy_texture_to_texture(
                             TexelCopyTextureInfo {
                                 texture,
@@ -191,6 +401,20 @@ impl Compositor {
                 }
             }
         }
+        if rotation != Rotation::None {
+            application_user_data
+                .rotated_presenter
+                .get_or_init(|| {
+                    RotatedPresenter::new(&application_user_data.device, frame.texture.format())
+                })
+                .present(
+                    &application_user_data.device,
+                    &mut encoder,
+                    &frame.texture,
+                    rotation,
+                    &rotated_layers,
+                );
+        }
         application_user_data.queue.submit(Some(encoder.finish()));
         frame.present();
         true
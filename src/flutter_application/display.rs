@@ -0,0 +1,122 @@
+use std::mem::size_of;
+
+use winit::{monitor::MonitorHandle, window::Window};
+
+use crate::flutter_bindings::{
+    FlutterEngine, FlutterEngineDisplay, FlutterEngineDisplayId,
+    FlutterEngineDisplaysUpdateType_kFlutterEngineDisplaysUpdateTypeStartup,
+    FlutterEngineNotifyDisplayUpdate,
+};
+
+use super::FlutterApplication;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DisplayInfo {
+    display_id: FlutterEngineDisplayId,
+    refresh_rate: f64,
+    width: u32,
+    height: u32,
+    device_pixel_ratio: f64,
+}
+
+/// Keeps track of the monitors winit knows about and reports them to the
+/// engine as `FlutterEngineDisplay`s.
+///
+/// winit doesn't emit an event when monitors are plugged in or removed, so the
+/// list is re-enumerated whenever the window metrics change (window moved,
+/// resized or scale factor changed) and only sent to the engine if it differs
+/// from what was reported before.
+#[derive(Default)]
+pub(super) struct Displays {
+    /// Every monitor seen so far. The index is used as the display id so ids
+    /// stay stable while monitors come and go.
+    known_monitors: Vec<MonitorHandle>,
+    displays: Vec<DisplayInfo>,
+}
+
+impl Displays {
+    /// Re-enumerates the available monitors. Returns `true` if the displays
+    /// changed since the last call.
+    pub(super) fn update(&mut self, window: &Window) -> bool {
+        let displays = window
+            .available_monitors()
+            .map(|monitor| {
+                let size = monitor.size();
+                DisplayInfo {
+                    refresh_rate: monitor
+                        .refresh_rate_millihertz()
+                        .map(|millihertz| millihertz as f64 / 1000.0)
+                        .unwrap_or(0.0),
+                    width: size.width,
+                    height: size.height,
+                    device_pixel_ratio: monitor.scale_factor(),
+                    display_id: self.id_for_monitor(monitor),
+                }
+            })
+            .collect::<Vec<_>>();
+        if displays == self.displays {
+            false
+        } else {
+            log::debug!("Displays changed: {displays:?}");
+            self.displays = displays;
+            true
+        }
+    }
+
+    fn id_for_monitor(&mut self, monitor: MonitorHandle) -> FlutterEngineDisplayId {
+        if let Some(idx) = self
+            .known_monitors
+            .iter()
+            .position(|known| *known == monitor)
+        {
+            idx as _
+        } else {
+            self.known_monitors.push(monitor);
+            (self.known_monitors.len() - 1) as _
+        }
+    }
+
+    /// The display id of `monitor`, falling back to the first display if the
+    /// monitor is unknown (e.g. on Wayland, where the current monitor isn't
+    /// always available).
+    pub(super) fn display_id(&self, monitor: Option<MonitorHandle>) -> FlutterEngineDisplayId {
+        monitor
+            .and_then(|monitor| {
+                self.known_monitors
+                    .iter()
+                    .position(|known| *known == monitor)
+            })
+            .map(|idx| idx as _)
+            .or_else(|| self.displays.first().map(|display| display.display_id))
+            .unwrap_or(0)
+    }
+
+    pub(super) fn notify_engine(&self, engine: FlutterEngine) {
+        if self.displays.is_empty() {
+            // The engine requires at least one display.
+            return;
+        }
+        let single_display = self.displays.len() == 1;
+        let displays = self
+            .displays
+            .iter()
+            .map(|display| FlutterEngineDisplay {
+                struct_size: size_of::<FlutterEngineDisplay>() as _,
+                display_id: display.display_id,
+                single_display,
+                refresh_rate: display.refresh_rate,
+                width: display.width as _,
+                height: display.height as _,
+                device_pixel_ratio: display.device_pixel_ratio,
+            })
+            .collect::<Vec<_>>();
+        FlutterApplication::unwrap_result(unsafe {
+            FlutterEngineNotifyDisplayUpdate(
+                engine,
+                FlutterEngineDisplaysUpdateType_kFlutterEngineDisplaysUpdateTypeStartup,
+                displays.as_ptr(),
+                displays.len() as _,
+            )
+        });
+    }
+}
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "method", content = "args")]
+pub(super) enum DropTargetMessage {
+    /// Files are dragged over the window. Sent again whenever the list of
+    /// files grows.
+    #[serde(rename = "DropTarget.hover")]
+    Hover(DropEvent),
+    /// The files were dropped onto the window.
+    #[serde(rename = "DropTarget.drop")]
+    Drop(DropEvent),
+    /// The drag left the window or was cancelled.
+    #[serde(rename = "DropTarget.leave")]
+    Leave,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(super) struct DropEvent {
+    /// The pointer position in logical pixels.
+    pub(super) x: f64,
+    pub(super) y: f64,
+    pub(super) paths: Vec<PathBuf>,
+}
+
+/// Collects the files of a drag and drop operation. winit reports each file
+/// separately, first hovered, then dropped, so the drop is complete once all
+/// hovered files were dropped.
+#[derive(Default)]
+pub(super) struct DropTarget {
+    hovered: Vec<PathBuf>,
+    dropped: Vec<PathBuf>,
+}
+
+impl DropTarget {
+    /// Returns all files hovered so far.
+    pub(super) fn hovered(&mut self, path: PathBuf) -> Vec<PathBuf> {
+        self.hovered.push(path);
+        self.hovered.clone()
+    }
+
+    /// Returns all dropped files once the last one was dropped.
+    pub(super) fn dropped(&mut self, path: PathBuf) -> Option<Vec<PathBuf>> {
+        self.hovered.retain(|hovered| hovered != &path);
+        self.dropped.push(path);
+        if self.hovered.is_empty() {
+            Some(std::mem::take(&mut self.dropped))
+        } else {
+            None
+        }
+    }
+
+    pub(super) fn cancelled(&mut self) {
+        self.hovered.clear();
+        self.dropped.clear();
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    borrow::Cow,
+    io::Cursor,
+    process::{Command, Stdio},
+};
+
+use rodio::{Decoder, OutputStream, OutputStreamHandle, PlayError, Source, StreamError};
+use serde::{Deserialize, Serialize};
+
+const CLICK_SOUND: &[u8] = include_bytes!("../../sounds/click.wav");
+const ALERT_SOUND: &[u8] = include_bytes!("../../sounds/alert.wav");
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SystemSoundType {
+    /// A short click, e.g. when a button is tapped.
+    #[serde(rename = "SystemSoundType.click")]
+    Click,
+    /// An alert, e.g. when a dialog opens.
+    #[serde(rename = "SystemSoundType.alert")]
+    Alert,
+    /// Sounds added by newer versions of Flutter.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum HapticFeedbackType {
+    /// `HapticFeedback.vibrate` without an argument, a vibration of a
+    /// system-default duration.
+    #[serde(skip)]
+    Vibrate,
+    #[serde(rename = "HapticFeedbackType.lightImpact")]
+    LightImpact,
+    #[serde(rename = "HapticFeedbackType.mediumImpact")]
+    MediumImpact,
+    #[serde(rename = "HapticFeedbackType.heavyImpact")]
+    HeavyImpact,
+    #[serde(rename = "HapticFeedbackType.selectionClick")]
+    SelectionClick,
+}
+
+/// Plays the sounds requested through `SystemSound.play`.
+pub trait SoundBackend {
+    fn play(&self, sound: SystemSoundType);
+}
+
+/// Plays nothing, e.g. for kiosks and headless setups.
+pub struct NullSoundBackend;
+
+impl SoundBackend for NullSoundBackend {
+    fn play(&self, sound: SystemSoundType) {
+        log::debug!("Not playing {sound:?}");
+    }
+}
+
+/// Plays the sounds on the default audio output, the bundled ones unless
+/// they are replaced.
+pub struct AudioSoundBackend {
+    // Playback stops when the stream is dropped.
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    click: Cow<'static, [u8]>,
+    alert: Cow<'static, [u8]>,
+}
+
+impl AudioSoundBackend {
+    pub fn new() -> Result<Self, StreamError> {
+        let (stream, handle) = OutputStream::try_default()?;
+        Ok(Self {
+            _stream: stream,
+            handle,
+            click: Cow::Borrowed(CLICK_SOUND),
+            alert: Cow::Borrowed(ALERT_SOUND),
+        })
+    }
+
+    /// Replaces a bundled sound, `sound_file` is the content of a WAV file.
+    pub fn set_sound(&mut self, sound: SystemSoundType, sound_file: Vec<u8>) {
+        match sound {
+            SystemSoundType::Click => self.click = Cow::Owned(sound_file),
+            SystemSoundType::Alert => self.alert = Cow::Owned(sound_file),
+            SystemSoundType::Unknown => {}
+        }
+    }
+
+    fn play_sound_file(&self, sound_file: Cow<'static, [u8]>) -> Result<(), PlayError> {
+        let source = Decoder::new_wav(Cursor::new(sound_file))?;
+        self.handle.play_raw(source.convert_samples())
+    }
+}
+
+impl SoundBackend for AudioSoundBackend {
+    fn play(&self, sound: SystemSoundType) {
+        let sound_file = match sound {
+            SystemSoundType::Click => self.click.clone(),
+            SystemSoundType::Alert => self.alert.clone(),
+            SystemSoundType::Unknown => return,
+        };
+        if let Err(err) = self.play_sound_file(sound_file) {
+            log::error!("Failed to play {sound:?}: {err}");
+        }
+    }
+}
+
+/// Runs `program` with the feedback type, e.g. `mediumImpact`, as its only
+/// argument. Meant for devices with a vibration motor driven by a script.
+pub fn command_haptic_handler(program: String) -> impl Fn(HapticFeedbackType) + 'static {
+    move |feedback_type| {
+        let argument = match feedback_type {
+            HapticFeedbackType::Vibrate => "vibrate",
+            HapticFeedbackType::LightImpact => "lightImpact",
+            HapticFeedbackType::MediumImpact => "mediumImpact",
+            HapticFeedbackType::HeavyImpact => "heavyImpact",
+            HapticFeedbackType::SelectionClick => "selectionClick",
+        };
+        spawn(Command::new(&program).arg(argument));
+    }
+}
+
+/// Starts the command without waiting for it, feedback must not block the
+/// platform thread.
+fn spawn(command: &mut Command) {
+    let program = command.get_program().to_owned();
+    match command.stdin(Stdio::null()).spawn() {
+        // Reaps the child once it's done.
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(err) => log::error!("Failed to run {program:?}: {err}"),
+    }
+}
@@ -1,89 +1,247 @@
 use std::{
+    collections::{HashMap, VecDeque},
     ffi::CString,
     mem::size_of,
+    ops::Range,
+    os::raw::c_void,
     ptr::{null, null_mut},
-    sync::{Arc, Mutex},
 };
 
-use arboard::Clipboard;
+use serde::Serialize;
 use winit::{
-    event::{ElementState, KeyEvent},
+    dpi::{LogicalPosition, LogicalSize, PhysicalPosition},
+    event::{ElementState, Ime, KeyEvent},
+    event_loop::EventLoopProxy,
     keyboard::{Key, ModifiersState, NamedKey, PhysicalKey},
+    platform::scancode::PhysicalKeyExtScancode,
+    window::Window,
 };
 
 use crate::{
     action_key::ActionKey,
-    flutter_application::{text_input::TextInputClient, FlutterApplication},
+    flutter_application::{
+        text_input::TextInputClient, FlutterApplication, FlutterApplicationCallback,
+    },
     flutter_bindings::{
         FlutterEngine, FlutterEngineSendKeyEvent, FlutterEngineSendPlatformMessage,
         FlutterKeyEvent, FlutterKeyEventDeviceType_kFlutterKeyEventDeviceTypeKeyboard,
         FlutterKeyEventType_kFlutterKeyEventTypeDown,
         FlutterKeyEventType_kFlutterKeyEventTypeRepeat, FlutterKeyEventType_kFlutterKeyEventTypeUp,
-        FlutterPlatformMessage,
+        FlutterPlatformMessage, FlutterPlatformMessageCreateResponseHandle,
+        FlutterPlatformMessageReleaseResponseHandle,
     },
+    keyboard_gtk_keyval_map::translate_gtk_keyval,
     keyboard_logical_key_map::translate_logical_key,
     keyboard_physical_key_map::translate_physical_key,
 };
 
 use super::{
-    text_input::{TextEditingValue, TextInput, TextInputAction},
-    FLUTTER_TEXTINPUT_CHANNEL,
+    clipboard::{Clipboard, ClipboardError},
+    keyboard_event::{
+        gtk_modifiers, FlutterKeyboardEvent, FlutterKeyboardEventResponse,
+        FlutterKeyboardEventType, LinuxToolkit,
+    },
+    orientation::Rotation,
+    text_editing,
+    text_input::{
+        AutofillConfiguration, EditableSizeAndTransform, Rect, TextEditingDelta, TextEditingDeltas,
+        TextEditingValue, TextInput, TextInputAction, TextInputType,
+    },
+    undo_manager::{UndoDirection, UndoHistory, UndoManager, UndoManagerClient, UndoState},
+    FLUTTER_KEYEVENT_CHANNEL, FLUTTER_TEXTINPUT_CHANNEL, FLUTTER_UNDOMANAGER_CHANNEL,
 };
 
+/// A key event waiting for the framework to report whether it handled it.
+struct PendingKeyEvent {
+    id: u64,
+    event: KeyEvent,
+    /// The modifiers at the time of the event, they might have changed by the
+    /// time the replies arrive.
+    modifiers: ModifiersState,
+    /// The event is sent both through `FlutterEngineSendKeyEvent` and on
+    /// `flutter/keyevent`, and counts as handled if either says so.
+    outstanding_replies: u8,
+    handled: bool,
+}
+
+/// The user data of the reply callbacks for a key event.
+struct KeyEventReply {
+    id: u64,
+    event_loop_proxy: EventLoopProxy<FlutterApplicationCallback>,
+}
+
 pub struct Keyboard {
     client: Option<u64>,
     modifiers: ModifiersState,
     editing_state: TextEditingValue,
-    clipboard: Arc<Mutex<Clipboard>>,
+    /// The editing state as last seen by the framework, which deltas are
+    /// based on.
+    framework_editing_state: TextEditingValue,
+    enable_delta_model: bool,
+    clipboard: Clipboard,
     input_action: TextInputAction,
+    input_type: TextInputType,
+    /// The column that vertical caret movement tries to keep.
+    preferred_column: Option<usize>,
+    undo_history: UndoHistory,
+    /// The state of the framework's own undo history for the client.
+    framework_undo_state: UndoState,
+    undo_manager_channel: CString,
+    obscure_text: bool,
+    primary_selection_enabled: bool,
+    /// The text last put into the PRIMARY selection.
+    primary_selection: Option<String>,
     channel: CString,
+    keyevent_channel: CString,
+    event_loop_proxy: EventLoopProxy<FlutterApplicationCallback>,
+    pending_events: VecDeque<PendingKeyEvent>,
+    next_event_id: u64,
+    editable: Option<EditableSizeAndTransform>,
+    marked_text_rect: Option<Rect>,
+    /// How Flutter's output is rotated in the window.
+    rotation: Rotation,
+    caret_rect: Option<Rect>,
+    /// The autofill configurations of the current autofill context, by their
+    /// unique identifier.
+    autofill_context: HashMap<String, AutofillConfiguration>,
 }
 
 impl Keyboard {
-    pub(super) fn new(clipboard: Arc<Mutex<Clipboard>>) -> Self {
+    pub(super) fn new(
+        clipboard: Clipboard,
+        event_loop_proxy: EventLoopProxy<FlutterApplicationCallback>,
+    ) -> Self {
         Self {
             client: None,
             modifiers: Default::default(),
             editing_state: Default::default(),
+            framework_editing_state: Default::default(),
+            enable_delta_model: false,
             clipboard,
             input_action: TextInputAction::Unspecified,
+            input_type: TextInputType::Text,
+            preferred_column: None,
+            undo_history: Default::default(),
+            framework_undo_state: Default::default(),
+            undo_manager_channel: CString::new(FLUTTER_UNDOMANAGER_CHANNEL).unwrap(),
+            obscure_text: false,
+            primary_selection_enabled: true,
+            primary_selection: None,
             channel: CString::new(FLUTTER_TEXTINPUT_CHANNEL).unwrap(),
+            keyevent_channel: CString::new(FLUTTER_KEYEVENT_CHANNEL).unwrap(),
+            event_loop_proxy,
+            pending_events: Default::default(),
+            next_event_id: 0,
+            editable: None,
+            marked_text_rect: None,
+            rotation: Rotation::None,
+            caret_rect: None,
+            autofill_context: Default::default(),
         }
     }
     pub(super) fn modifiers_changed(&mut self, state: ModifiersState) {
         self.modifiers = state;
     }
 
-    fn move_home(&mut self) {
-        self.editing_state.selection_base = Some(0);
+    pub(super) fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// The selection as an ordered range.
+    fn selection(&self) -> Range<usize> {
+        let base = self.editing_state.selection_base.unwrap_or(0).max(0) as usize;
+        let extent = self.editing_state.selection_extent.unwrap_or(0).max(0) as usize;
+        base.min(extent)..base.max(extent)
+    }
+
+    fn selection_extent(&self) -> usize {
+        self.editing_state.selection_extent.unwrap_or(0).max(0) as usize
+    }
+
+    fn selected_text(&self) -> String {
+        let text = &self.editing_state.text;
+        text[text_editing::byte_range(text, self.selection())].to_owned()
+    }
+
+    fn set_selection(&mut self, base: usize, extent: usize) {
+        self.editing_state.selection_base = Some(base as _);
+        self.editing_state.selection_extent = Some(extent as _);
+    }
+
+    /// Moves the end of the selection to `offset`. Unless Shift is held, the
+    /// selection collapses there.
+    fn move_selection_extent(&mut self, offset: usize) {
+        self.editing_state.selection_extent = Some(offset as _);
         if !self.modifiers.shift_key() {
-            self.editing_state.selection_extent = Some(0);
+            self.editing_state.selection_base = Some(offset as _);
         }
     }
 
+    fn is_multiline(&self) -> bool {
+        matches!(self.input_type, TextInputType::Multiline)
+    }
+
+    fn move_line_start(&mut self) {
+        let offset = text_editing::line_start(&self.editing_state.text, self.selection_extent());
+        self.move_selection_extent(offset);
+    }
+
+    fn move_line_end(&mut self) {
+        let offset = text_editing::line_end(&self.editing_state.text, self.selection_extent());
+        self.move_selection_extent(offset);
+    }
+
+    /// Moves the caret to the previous or next line, keeping the column of
+    /// where the vertical movement started. The embedder doesn't know the
+    /// text layout, so this only follows explicit line breaks, and the
+    /// columns are counted in grapheme clusters rather than measured. The
+    /// caret rect can't help with lines wrapped by the layout, as there's no
+    /// way to map a position back to an offset. The framework's own shortcuts
+    /// handle those, this is only reached for keys it didn't handle.
+    fn move_vertically(&mut self, up: bool) {
+        let text = &self.editing_state.text;
+        let extent = self.selection_extent();
+        let column = self
+            .preferred_column
+            .unwrap_or_else(|| text_editing::column(text, extent));
+        let offset = if up {
+            let start = text_editing::line_start(text, extent);
+            if start == 0 {
+                0
+            } else {
+                let previous_start = text_editing::line_start(text, start - 1);
+                text_editing::offset_in_line(text, previous_start, column)
+            }
+        } else {
+            let end = text_editing::line_end(text, extent);
+            if end == text_editing::utf16_len(text) {
+                end
+            } else {
+                text_editing::offset_in_line(text, end + 1, column)
+            }
+        };
+        self.move_selection_extent(offset);
+        self.preferred_column = Some(column);
+    }
+
+    fn move_home(&mut self) {
+        self.move_selection_extent(0);
+    }
+
     fn move_end(&mut self) {
-        let len = self.editing_state.text.chars().count();
-        self.editing_state.selection_extent = Some(len as _);
-        if !self.modifiers.shift_key() {
-            self.editing_state.selection_base = self.editing_state.selection_extent;
-        }
+        self.move_selection_extent(text_editing::utf16_len(&self.editing_state.text));
+    }
+
+    /// Replaces `range` with `text` and puts the caret after it.
+    fn replace_range(&mut self, range: Range<usize>, text: &str) {
+        let byte_range = text_editing::byte_range(&self.editing_state.text, range.clone());
+        self.editing_state.text.replace_range(byte_range, text);
+        let caret = range.start + text_editing::utf16_len(text);
+        self.set_selection(caret, caret);
     }
 
     fn insert_text(&mut self, text: &str) {
-        let editing_state = &mut self.editing_state;
-        let len = editing_state.text.chars().count();
-        let selection_base = editing_state.selection_base.unwrap_or(0) as usize;
-        let selection_extent = editing_state.selection_extent.unwrap_or(0) as usize;
-        let selection = selection_base.min(selection_extent)..selection_base.max(selection_extent);
-
-        if len > 0 && selection.start < len {
-            editing_state.text.replace_range(selection.clone(), text);
-            editing_state.selection_base = Some((selection.start + text.chars().count()) as _);
-        } else {
-            editing_state.text.push_str(text);
-            editing_state.selection_base = Some(editing_state.text.chars().count() as _);
-        }
-        editing_state.selection_extent = editing_state.selection_base;
+        self.replace_range(self.selection(), text);
     }
 
     pub(super) fn key_event(&mut self, engine: FlutterEngine, event: KeyEvent, synthesized: bool) {
@@ -108,55 +266,6 @@ impl Keyboard {
             translate_logical_key(event.logical_key.clone()),
             translate_physical_key(physical_key),
         ) {
-            // let flutter_event = FlutterKeyboardEvent::Linux {
-            //     r#type: match event.state {
-            //         ElementState::Pressed => FlutterKeyboardEventType::KeyDown,
-            //         ElementState::Released => FlutterKeyboardEventType::KeyUp,
-            //     },
-            //     toolkit: LinuxToolkit::Gtk,
-            //     unicode_scalar_values: if let Some(character) = event.text {
-            //         let mut buffer = [0u8; 8];
-            //         if character.as_bytes().read(&mut buffer).is_ok() {
-            //             u64::from_le_bytes(buffer)
-            //         } else {
-            //             0
-            //         }
-            //     } else {
-            //         0
-            //     },
-            //     key_code: physical,
-            //     scan_code: logical,
-            //     modifiers: 0,
-            //     specified_logical_key: 0,
-            // };
-            // let flutter_event = FlutterKeyboardEvent::Web {
-            //     r#type: match event.state {
-            //         ElementState::Pressed => FlutterKeyboardEventType::KeyDown,
-            //         ElementState::Released => FlutterKeyboardEventType::KeyUp,
-            //     },
-            //     code: event.text.unwrap_or_default().to_owned(),
-            //     key: event.text.unwrap_or_default().to_owned(),
-            //     location: 0,
-            //     meta_state: 0,
-            //     key_code: 0,
-            // };
-
-            // let json = serde_json::to_vec(&flutter_event).unwrap();
-            // log::debug!("keyevent: {:?}", String::from_utf8(json.clone()));
-            // let channel = CStr::from_bytes_with_nul(b"flutter/keyevent\0").unwrap();
-            // let message = FlutterPlatformMessage {
-            //     struct_size: size_of::<FlutterPlatformMessage>() as _,
-            //     channel: channel.as_ptr(),
-            //     message: json.as_ptr(),
-            //     message_size: json.len() as _,
-            //     response_handle: null(),
-            // };
-
-            // Self::unwrap_result(unsafe { FlutterEngineSendPlatformMessage(self.engine, &message) });
-
-            // drop(message);
-            // drop(channel);
-
             let type_ = match event.state {
                 ElementState::Pressed => {
                     if event.repeat {
@@ -191,255 +300,725 @@ impl Keyboard {
                 synthesized,
                 device_type: FlutterKeyEventDeviceType_kFlutterKeyEventDeviceTypeKeyboard,
             };
+            if synthesized {
+                // Synthesized events only keep the framework's key state in
+                // sync, they don't exist for RawKeyboard and never edit text.
+                FlutterApplication::unwrap_result(unsafe {
+                    FlutterEngineSendKeyEvent(engine, &flutter_event, None, null_mut())
+                });
+                return;
+            }
+
+            let id = self.next_event_id;
+            self.next_event_id += 1;
             FlutterApplication::unwrap_result(unsafe {
-                FlutterEngineSendKeyEvent(engine, &flutter_event, None, null_mut())
+                FlutterEngineSendKeyEvent(
+                    engine,
+                    &flutter_event,
+                    Some(Self::key_event_callback),
+                    self.reply_user_data(id),
+                )
             });
             drop(character);
 
-            log::debug!(
-                "Updating editing state for keyboard client {:?}",
-                self.client
-            );
+            self.send_raw_key_event(engine, id, &event);
+            self.pending_events.push_back(PendingKeyEvent {
+                id,
+                event,
+                modifiers: self.modifiers,
+                outstanding_replies: 2,
+                handled: false,
+            });
+        }
+    }
 
-            if event.state == ElementState::Pressed
-                && self
-                    .editing_state
-                    .selection_base
-                    .map(|val| val >= 0)
-                    .unwrap_or(false)
-                && self
-                    .editing_state
-                    .selection_extent
-                    .map(|val| val >= 0)
-                    .unwrap_or(false)
-            {
-                // send flutter/textinput message
-                {
-                    let editing_state = &mut self.editing_state;
-                    let len = editing_state.text.chars().count();
-                    let selection_base = editing_state.selection_base.unwrap_or(0) as usize;
-                    let selection_extent = editing_state.selection_extent.unwrap_or(0) as usize;
-                    let selection =
-                        selection_base.min(selection_extent)..selection_base.max(selection_extent);
-                    match event.logical_key {
-                        Key::Named(key) => match key {
-                            #[cfg(any(target_os = "macos", target_os = "ios"))]
-                            NamedKey::ArrowLeft if self.keyboard_modifiers.meta_key() => {
-                                self.move_home();
-                            }
-                            #[cfg(any(target_os = "macos", target_os = "ios"))]
-                            NamedKey::ArrowRight if self.keyboard_modifiers.meta_key() => {
-                                self.move_end();
-                            }
-                            NamedKey::ArrowLeft => {
-                                if selection.start > 0 {
-                                    if !self.modifiers.shift_key()
-                                        && selection.start != selection.end
-                                    {
-                                        editing_state.selection_extent =
-                                            editing_state.selection_base;
-                                    } else {
-                                        editing_state.selection_base =
-                                            Some((selection.start - 1) as _);
-                                        if !self.modifiers.shift_key() {
-                                            editing_state.selection_extent =
-                                                editing_state.selection_base;
-                                        }
-                                    }
-                                } else if !self.modifiers.shift_key()
-                                    && selection.start != selection.end
-                                {
-                                    editing_state.selection_extent = editing_state.selection_base;
-                                }
-                            }
-                            NamedKey::ArrowRight => {
-                                if selection.end < len {
-                                    if !self.modifiers.shift_key()
-                                        && selection.start != selection.end
-                                    {
-                                        editing_state.selection_base =
-                                            editing_state.selection_extent;
-                                    } else {
-                                        editing_state.selection_extent =
-                                            Some((selection.end + 1) as _);
-                                        if !self.modifiers.shift_key() {
-                                            editing_state.selection_base =
-                                                editing_state.selection_extent;
-                                        }
-                                    }
-                                } else if !self.modifiers.shift_key()
-                                    && selection.start != selection.end
-                                {
-                                    editing_state.selection_base = editing_state.selection_extent;
-                                }
-                            }
-                            NamedKey::ArrowUp | NamedKey::Home => {
-                                self.move_home();
-                            }
-                            NamedKey::ArrowDown | NamedKey::End => {
-                                self.move_end();
-                            }
-                            NamedKey::Backspace => {
-                                if selection.start == selection.end {
-                                    if selection.start > 0 {
-                                        editing_state.text.remove(selection.start - 1);
-                                        editing_state.selection_base =
-                                            Some((selection.start - 1) as _);
-                                    }
-                                    editing_state.selection_extent = editing_state.selection_base;
-                                } else {
-                                    editing_state.text.replace_range(selection.clone(), "");
-                                    editing_state.selection_extent = editing_state.selection_base;
-                                }
-                            }
-                            NamedKey::Delete => {
-                                if selection.start == selection.end {
-                                    if selection.start < len {
-                                        editing_state.text.remove(selection.start);
-                                    }
-                                } else {
-                                    editing_state.text.replace_range(selection.clone(), "");
-                                    editing_state.selection_extent = editing_state.selection_base;
+    fn reply_user_data(&self, id: u64) -> *mut c_void {
+        Box::into_raw(Box::new(KeyEventReply {
+            id,
+            event_loop_proxy: self.event_loop_proxy.clone(),
+        })) as _
+    }
+
+    /// Sends the event on the legacy `flutter/keyevent` channel, formatted
+    /// like the GTK embedder does. Always sent, the framework expects each
+    /// key data event to be followed by a raw one.
+    fn send_raw_key_event(&self, engine: FlutterEngine, id: u64, event: &KeyEvent) {
+        let unicode_scalar_values = match &event.logical_key {
+            Key::Named(NamedKey::Delete) => 0x7f,
+            key => key
+                .to_text()
+                .and_then(|text| text.chars().next())
+                .map(|c| c as u64)
+                .unwrap_or(0),
+        };
+        // Keys without a GTK keyval fall back to the keyval GTK uses for
+        // their character, if they have one.
+        let key_code = translate_gtk_keyval(&event.logical_key, event.location).unwrap_or(
+            match unicode_scalar_values {
+                0 => 0,
+                code_point => 0x01000000 | code_point,
+            },
+        );
+        let raw_event = FlutterKeyboardEvent::Linux {
+            r#type: match event.state {
+                ElementState::Pressed => FlutterKeyboardEventType::KeyDown,
+                ElementState::Released => FlutterKeyboardEventType::KeyUp,
+            },
+            toolkit: LinuxToolkit::Gtk,
+            unicode_scalar_values,
+            key_code,
+            // GTK reports X11 keycodes, which are offset by 8 from the
+            // evdev scancodes winit uses.
+            scan_code: event
+                .physical_key
+                .to_scancode()
+                .map(|scancode| scancode as u64 + 8)
+                .unwrap_or(0),
+            modifiers: gtk_modifiers(self.modifiers),
+            specified_logical_key: None,
+        };
+        let message = serde_json::to_vec(&raw_event).unwrap();
+
+        let mut response_handle = null_mut();
+        FlutterApplication::unwrap_result(unsafe {
+            FlutterPlatformMessageCreateResponseHandle(
+                engine,
+                Some(Self::raw_key_event_response),
+                self.reply_user_data(id),
+                &mut response_handle,
+            )
+        });
+        FlutterApplication::unwrap_result(unsafe {
+            FlutterEngineSendPlatformMessage(
+                engine,
+                &FlutterPlatformMessage {
+                    struct_size: size_of::<FlutterPlatformMessage>() as _,
+                    channel: self.keyevent_channel.as_ptr(),
+                    message: message.as_ptr(),
+                    message_size: message.len() as _,
+                    response_handle,
+                },
+            )
+        });
+        FlutterApplication::unwrap_result(unsafe {
+            FlutterPlatformMessageReleaseResponseHandle(engine, response_handle)
+        });
+    }
+
+    extern "C" fn key_event_callback(handled: bool, user_data: *mut c_void) {
+        Self::reply(user_data, handled);
+    }
+
+    extern "C" fn raw_key_event_response(data: *const u8, size: usize, user_data: *mut c_void) {
+        // An empty reply means that nobody listens on the channel.
+        let handled = if data.is_null() || size == 0 {
+            false
+        } else {
+            let data = unsafe { std::slice::from_raw_parts(data, size) };
+            serde_json::from_slice::<FlutterKeyboardEventResponse>(data)
+                .map(|response| response.handled)
+                .unwrap_or_else(|err| {
+                    log::error!(
+                        "Invalid keyevent response {:?}: {err}",
+                        std::str::from_utf8(data)
+                    );
+                    false
+                })
+        };
+        Self::reply(user_data, handled);
+    }
+
+    fn reply(user_data: *mut c_void, handled: bool) {
+        let reply = unsafe { Box::from_raw(user_data as *mut KeyEventReply) };
+        let KeyEventReply {
+            id,
+            event_loop_proxy,
+        } = *reply;
+        event_loop_proxy
+            .send_event(Box::new(move |application| {
+                application
+                    .keyboard
+                    .key_event_handled(application.engine, id, handled);
+                false
+            }))
+            .ok()
+            .unwrap();
+    }
+
+    /// Records a reply from the framework. Events are processed in the order
+    /// they were sent, so an event whose replies are complete waits for all
+    /// earlier events before its text editing fallback is applied.
+    fn key_event_handled(&mut self, engine: FlutterEngine, id: u64, handled: bool) {
+        let Some(pending) = self.pending_events.iter_mut().find(|event| event.id == id) else {
+            log::warn!("Reply for unknown key event {id}");
+            return;
+        };
+        pending.outstanding_replies -= 1;
+        pending.handled |= handled;
+
+        while self
+            .pending_events
+            .front()
+            .map(|event| event.outstanding_replies == 0)
+            .unwrap_or(false)
+        {
+            let pending = self.pending_events.pop_front().unwrap();
+            if pending.handled {
+                continue;
+            }
+            let modifiers = std::mem::replace(&mut self.modifiers, pending.modifiers);
+            self.text_editing_fallback(engine, pending.event);
+            self.modifiers = modifiers;
+        }
+    }
+
+    /// Applies the embedder's own text editing for a key event the framework
+    /// didn't handle.
+    fn text_editing_fallback(&mut self, engine: FlutterEngine, event: KeyEvent) {
+        log::debug!(
+            "Updating editing state for keyboard client {:?}",
+            self.client
+        );
+
+        if event.state == ElementState::Pressed
+            && self
+                .editing_state
+                .selection_base
+                .map(|val| val >= 0)
+                .unwrap_or(false)
+            && self
+                .editing_state
+                .selection_extent
+                .map(|val| val >= 0)
+                .unwrap_or(false)
+        {
+            // Only consecutive vertical movements keep their column.
+            let preferred_column = self.preferred_column.take();
+            let previous_state = self.editing_state.clone();
+            let mut typing = false;
+            let mut undoing = false;
+            let shift = self.modifiers.shift_key();
+            let by_word = self.modifiers.word_key();
+            let selection = self.selection();
+            let text = &self.editing_state.text;
+            match event.logical_key {
+                Key::Named(key) => match key {
+                    #[cfg(any(target_os = "macos", target_os = "ios"))]
+                    NamedKey::ArrowLeft if self.modifiers.super_key() => {
+                        self.move_line_start();
+                    }
+                    #[cfg(any(target_os = "macos", target_os = "ios"))]
+                    NamedKey::ArrowRight if self.modifiers.super_key() => {
+                        self.move_line_end();
+                    }
+                    #[cfg(any(target_os = "macos", target_os = "ios"))]
+                    NamedKey::ArrowUp if self.modifiers.super_key() => {
+                        self.move_home();
+                    }
+                    #[cfg(any(target_os = "macos", target_os = "ios"))]
+                    NamedKey::ArrowDown if self.modifiers.super_key() => {
+                        self.move_end();
+                    }
+                    NamedKey::ArrowLeft => {
+                        if !shift && !selection.is_empty() {
+                            self.move_selection_extent(selection.start);
+                        } else if by_word {
+                            let offset = text_editing::previous_word(text, self.selection_extent());
+                            self.move_selection_extent(offset);
+                        } else {
+                            let offset =
+                                text_editing::previous_grapheme(text, self.selection_extent());
+                            self.move_selection_extent(offset);
+                        }
+                    }
+                    NamedKey::ArrowRight => {
+                        if !shift && !selection.is_empty() {
+                            self.move_selection_extent(selection.end);
+                        } else if by_word {
+                            let offset = text_editing::next_word(text, self.selection_extent());
+                            self.move_selection_extent(offset);
+                        } else {
+                            let offset = text_editing::next_grapheme(text, self.selection_extent());
+                            self.move_selection_extent(offset);
+                        }
+                    }
+                    NamedKey::ArrowUp if self.is_multiline() => {
+                        self.preferred_column = preferred_column;
+                        self.move_vertically(true);
+                    }
+                    NamedKey::ArrowDown if self.is_multiline() => {
+                        self.preferred_column = preferred_column;
+                        self.move_vertically(false);
+                    }
+                    NamedKey::Home if self.is_multiline() && !self.modifiers.action_key() => {
+                        self.move_line_start();
+                    }
+                    NamedKey::End if self.is_multiline() && !self.modifiers.action_key() => {
+                        self.move_line_end();
+                    }
+                    NamedKey::ArrowUp | NamedKey::Home => {
+                        self.move_home();
+                    }
+                    NamedKey::ArrowDown | NamedKey::End => {
+                        self.move_end();
+                    }
+                    NamedKey::Backspace => {
+                        if selection.is_empty() {
+                            let start = if by_word {
+                                text_editing::previous_word(text, selection.start)
+                            } else {
+                                text_editing::previous_grapheme(text, selection.start)
+                            };
+                            self.replace_range(start..selection.start, "");
+                        } else {
+                            self.replace_range(selection, "");
+                        }
+                    }
+                    NamedKey::Delete => {
+                        if selection.is_empty() {
+                            let end = if by_word {
+                                text_editing::next_word(text, selection.end)
+                            } else {
+                                text_editing::next_grapheme(text, selection.end)
+                            };
+                            self.replace_range(selection.start..end, "");
+                        } else {
+                            self.replace_range(selection, "");
+                        }
+                    }
+                    NamedKey::Enter => {
+                        // Any other action is performed instead, even in a
+                        // multiline field.
+                        if self.is_multiline()
+                            && matches!(self.input_action, TextInputAction::Newline)
+                        {
+                            self.insert_text("\n");
+                        } else {
+                            self.send_action(engine, self.input_action);
+                        }
+                    }
+                    NamedKey::Tab => {
+                        if shift {
+                            self.send_action(engine, TextInputAction::Previous);
+                        } else {
+                            self.send_action(engine, TextInputAction::Next);
+                        }
+                    }
+                    _ => {
+                        if let Some(text) = event.text {
+                            self.insert_text(text.as_str());
+                            typing = true;
+                        }
+                    }
+                },
+                Key::Character(c) => {
+                    match c.as_str() {
+                        "a" if self.modifiers.action_key() => {
+                            let len = text_editing::utf16_len(text);
+                            self.set_selection(0, len);
+                        }
+                        #[cfg(any(target_os = "macos", target_os = "ios"))]
+                        "a" if self.modifiers.control_key() => {
+                            self.move_home();
+                        }
+                        #[cfg(any(target_os = "macos", target_os = "ios"))]
+                        "e" if self.modifiers.control_key() => {
+                            self.move_end();
+                        }
+                        "x" if self.modifiers.action_key() => {
+                            if !selection.is_empty() {
+                                let text = self.selected_text();
+                                self.replace_range(selection, "");
+                                if let Err(err) = self.clipboard.set_text(&text) {
+                                    log::error!("Failed setting clipboard: {err}");
                                 }
                             }
-                            NamedKey::Enter => {
-                                self.send_action(engine, self.input_action);
-                            }
-                            NamedKey::Tab => {
-                                if self.modifiers.shift_key() {
-                                    self.send_action(engine, TextInputAction::Previous);
-                                } else {
-                                    self.send_action(engine, TextInputAction::Next);
+                        }
+                        "c" if self.modifiers.action_key() => {
+                            if !selection.is_empty() {
+                                let text = self.selected_text();
+                                if let Err(err) = self.clipboard.set_text(&text) {
+                                    log::error!("Failed setting clipboard: {err}");
                                 }
                             }
-                            _ => {
-                                if let Some(text) = event.text {
-                                    self.insert_text(text.as_str());
-                                }
+                        }
+                        "z" | "Z" if self.modifiers.action_key() && shift => {
+                            self.undo(engine, UndoDirection::Redo);
+                            undoing = true;
+                        }
+                        "z" | "Z" if self.modifiers.action_key() => {
+                            self.undo(engine, UndoDirection::Undo);
+                            undoing = true;
+                        }
+                        "y" | "Y" if self.modifiers.action_key() => {
+                            self.undo(engine, UndoDirection::Redo);
+                            undoing = true;
+                        }
+                        "v" if self.modifiers.action_key() => {
+                            if let Ok(text) = self.clipboard.text() {
+                                self.insert_text(&text);
                             }
-                        },
-                        Key::Character(c) => {
-                            match c.as_str() {
-                                "a" if self.modifiers.action_key() => {
-                                    editing_state.selection_base = Some(0);
-                                    editing_state.selection_extent = Some(len as _);
-                                }
-                                #[cfg(any(target_os = "macos", target_os = "ios"))]
-                                "a" if self.keyboard_modifiers.control_key() => {
-                                    self.move_home();
-                                }
-                                #[cfg(any(target_os = "macos", target_os = "ios"))]
-                                "e" if self.modifiers.control_key() => {
-                                    self.move_end();
-                                }
-                                "x" if self.modifiers.action_key() => {
-                                    if selection.start != selection.end {
-                                        let text = editing_state
-                                            .text
-                                            .chars()
-                                            .skip(selection.start)
-                                            .take(selection.end - selection.start)
-                                            .collect();
-                                        editing_state.text.replace_range(selection.clone(), "");
-                                        editing_state.selection_extent =
-                                            editing_state.selection_base;
-                                        self.clipboard.lock().unwrap().set_text(text).unwrap();
-                                    }
-                                }
-                                "c" if self.modifiers.action_key() => {
-                                    if selection.start != selection.end {
-                                        let text = editing_state
-                                            .text
-                                            .chars()
-                                            .skip(selection.start)
-                                            .take(selection.end - selection.start)
-                                            .collect();
-                                        self.clipboard.lock().unwrap().set_text(text).unwrap();
-                                    }
-                                }
-                                "v" if self.modifiers.action_key() => {
-                                    let text = {
-                                        let mut clipboard = self.clipboard.lock().unwrap();
-                                        clipboard.get_text()
-                                    };
-                                    if let Ok(text) = text {
-                                        self.insert_text(&text);
-                                    }
-                                }
-
-                                _ => {
-                                    self.insert_text(c.as_str());
-                                }
-                            };
                         }
 
-                        Key::Unidentified(unidentifed) => {
-                            log::warn!("Unidentified key {:?}", unidentifed)
+                        _ => {
+                            self.insert_text(c.as_str());
+                            typing = true;
                         }
-                        Key::Dead(dead) => log::warn!("Dead key {:?}", dead),
-                    }
+                    };
                 }
-                self.update_editing_state(engine);
+
+                Key::Unidentified(unidentifed) => {
+                    log::warn!("Unidentified key {:?}", unidentifed)
+                }
+                Key::Dead(dead) => log::warn!("Dead key {:?}", dead),
             }
+            if !undoing {
+                self.undo_history
+                    .record(&previous_state, &self.editing_state, typing);
+            }
+            self.update_editing_state(engine);
         }
     }
 
-    fn update_editing_state(&self, engine: FlutterEngine) {
+    fn update_editing_state(&mut self, engine: FlutterEngine) {
         if let Some(client) = self.client {
-            let message = TextInputClient::UpdateEditingState(client, self.editing_state.clone());
-            log::info!("update_editing_state message: {message:?}");
-            let message_json = serde_json::to_vec(&message).unwrap();
-            FlutterApplication::unwrap_result(unsafe {
-                FlutterEngineSendPlatformMessage(
-                    engine,
-                    &FlutterPlatformMessage {
-                        struct_size: size_of::<FlutterPlatformMessage>() as _,
-                        channel: self.channel.as_ptr(),
-                        message: message_json.as_ptr(),
-                        message_size: message_json.len() as _,
-                        response_handle: null(),
+            let message = if self.enable_delta_model {
+                TextInputClient::UpdateEditingStateWithDeltas(
+                    client,
+                    TextEditingDeltas {
+                        deltas: vec![TextEditingDelta::between(
+                            &self.framework_editing_state,
+                            &self.editing_state,
+                        )],
                     },
                 )
-            });
+            } else {
+                TextInputClient::UpdateEditingState(client, self.editing_state.clone())
+            };
+            self.framework_editing_state = self.editing_state.clone();
+            self.update_primary_selection();
+            log::info!("update_editing_state message: {message:?}");
+            Self::send_message(engine, &self.channel, &message);
         }
     }
 
     fn send_action(&self, engine: FlutterEngine, action: TextInputAction) {
         if let Some(client) = self.client {
             let message = TextInputClient::PerformAction(client, action);
-            let message_json = serde_json::to_vec(&message).unwrap();
-            FlutterApplication::unwrap_result(unsafe {
-                FlutterEngineSendPlatformMessage(
-                    engine,
-                    &FlutterPlatformMessage {
-                        struct_size: size_of::<FlutterPlatformMessage>() as _,
-                        channel: self.channel.as_ptr(),
-                        message: message_json.as_ptr(),
-                        message_size: message_json.len() as _,
-                        response_handle: null(),
-                    },
-                )
-            });
+            Self::send_message(engine, &self.channel, &message);
+        }
+    }
+
+    pub(super) fn set_primary_selection_enabled(&mut self, enabled: bool) {
+        self.primary_selection_enabled = enabled;
+    }
+
+    /// Puts selected text into the PRIMARY selection, like other Linux
+    /// applications do. Obscured text (passwords) is never exported.
+    fn update_primary_selection(&mut self) {
+        if !self.primary_selection_enabled || self.client.is_none() || self.obscure_text {
+            return;
+        }
+        let text = self.selected_text();
+        if text.is_empty() || self.primary_selection.as_ref() == Some(&text) {
+            return;
+        }
+        match self.clipboard.set_primary_selection_text(&text) {
+            Ok(()) | Err(ClipboardError::Unsupported) => {}
+            Err(err) => log::warn!("Failed setting the primary selection: {err}"),
+        }
+        self.primary_selection = Some(text);
+    }
+
+    /// Pastes the PRIMARY selection on a middle click, if `position` (in
+    /// logical coordinates) is within the focused text field.
+    pub(super) fn paste_primary_selection(&mut self, engine: FlutterEngine, position: (f64, f64)) {
+        if !self.primary_selection_enabled || self.client.is_none() {
+            return;
+        }
+        let Some(editable) = &self.editable else {
+            return;
+        };
+        let bounds = editable.transform_rect(Rect {
+            x: 0.0,
+            y: 0.0,
+            width: editable.width,
+            height: editable.height,
+        });
+        let (x, y) = position;
+        if x < bounds.x
+            || x > bounds.x + bounds.width
+            || y < bounds.y
+            || y > bounds.y + bounds.height
+        {
+            return;
+        }
+        let text = self
+            .clipboard
+            .primary_selection_text()
+            .map_err(|err| log::debug!("Nothing to paste from the primary selection: {err}"));
+        if let Ok(text) = text {
+            let previous_state = self.editing_state.clone();
+            // The framework doesn't move the caret on middle clicks, so the
+            // text is inserted at the current selection.
+            self.insert_text(&text);
+            self.undo_history
+                .record(&previous_state, &self.editing_state, false);
+            self.update_editing_state(engine);
         }
     }
 
-    pub(super) fn handle_textinput_message(&mut self, textinput: TextInput) {
+    fn send_message(engine: FlutterEngine, channel: &CString, message: &impl Serialize) {
+        let message_json = serde_json::to_vec(message).unwrap();
+        FlutterApplication::unwrap_result(unsafe {
+            FlutterEngineSendPlatformMessage(
+                engine,
+                &FlutterPlatformMessage {
+                    struct_size: size_of::<FlutterPlatformMessage>() as _,
+                    channel: channel.as_ptr(),
+                    message: message_json.as_ptr(),
+                    message_size: message_json.len() as _,
+                    response_handle: null(),
+                },
+            )
+        });
+    }
+
+    /// Undoes or redoes the last edit. If the framework keeps an undo history
+    /// for the client, it is asked to do it, so both stay in sync.
+    fn undo(&mut self, engine: FlutterEngine, direction: UndoDirection) {
+        let framework_can_undo = match direction {
+            UndoDirection::Undo => self.framework_undo_state.can_undo,
+            UndoDirection::Redo => self.framework_undo_state.can_redo,
+        };
+        if framework_can_undo {
+            let message = UndoManagerClient::HandlePlatformUndo((direction,));
+            Self::send_message(engine, &self.undo_manager_channel, &message);
+            return;
+        }
+        let state = match direction {
+            UndoDirection::Undo => self.undo_history.undo(&self.editing_state),
+            UndoDirection::Redo => self.undo_history.redo(&self.editing_state),
+        };
+        if let Some(state) = state {
+            self.editing_state = state;
+        }
+    }
+
+    pub(super) fn handle_undo_manager_message(&mut self, message: UndoManager) {
+        match message {
+            UndoManager::SetUndoState(state) => {
+                log::debug!("set undo state: {state:?}");
+                self.framework_undo_state = state;
+            }
+        }
+    }
+
+    /// The range the IME preedit replaces: the composing region if there is
+    /// one, otherwise the selection.
+    fn composing_range(&self) -> Range<usize> {
+        let editing_state = &self.editing_state;
+        let (base, extent) = match (editing_state.composing_base, editing_state.composing_extent) {
+            (Some(base), Some(extent)) if base >= 0 && extent >= 0 => (base, extent),
+            _ => (
+                editing_state.selection_base.unwrap_or(0).max(0),
+                editing_state.selection_extent.unwrap_or(0).max(0),
+            ),
+        };
+        base.min(extent) as usize..base.max(extent) as usize
+    }
+
+    fn is_composing(&self) -> bool {
+        matches!(self.editing_state.composing_base, Some(base) if base >= 0)
+    }
+
+    fn set_composing(&mut self, range: Option<Range<usize>>) {
+        let (base, extent) = range
+            .map(|range| (range.start as i64, range.end as i64))
+            .unwrap_or((-1, -1));
+        self.editing_state.composing_base = Some(base);
+        self.editing_state.composing_extent = Some(extent);
+    }
+
+    pub(super) fn ime(&mut self, engine: FlutterEngine, ime: Ime) {
+        if self.client.is_none() {
+            return;
+        }
+        match ime {
+            Ime::Enabled => {
+                log::debug!("IME enabled");
+                return;
+            }
+            Ime::Preedit(text, cursor) => {
+                let range = self.composing_range();
+                self.replace_range(range.clone(), &text);
+                if text.is_empty() {
+                    self.set_composing(None);
+                } else {
+                    let len = text_editing::utf16_len(&text);
+                    self.set_composing(Some(range.start..range.start + len));
+                    // The cursor is given as byte offsets into the preedit
+                    // text, no cursor means it should be hidden.
+                    let (base, extent) = cursor
+                        .map(|(base, extent)| {
+                            (
+                                text_editing::utf16_len(&text[..base]),
+                                text_editing::utf16_len(&text[..extent]),
+                            )
+                        })
+                        .unwrap_or((len, len));
+                    self.set_selection(range.start + base, range.start + extent);
+                }
+            }
+            Ime::Commit(text) => {
+                let mut range = self.composing_range();
+                // Preedit text isn't part of the undo history, the step
+                // starts from the text without it.
+                if self.is_composing() {
+                    self.replace_range(range.clone(), "");
+                    self.set_composing(None);
+                    range = range.start..range.start;
+                }
+                let previous_state = self.editing_state.clone();
+                self.replace_range(range, &text);
+                self.undo_history
+                    .record(&previous_state, &self.editing_state, true);
+            }
+            Ime::Disabled => {
+                // Anything the IME wanted to keep was committed before, so
+                // leftover preedit text is discarded.
+                if self.is_composing() {
+                    let range = self.composing_range();
+                    self.replace_range(range, "");
+                    self.set_composing(None);
+                } else {
+                    return;
+                }
+            }
+        }
+        self.update_editing_state(engine);
+    }
+
+    /// Moves the IME candidate window next to the composing text, or the
+    /// caret if nothing is being composed.
+    fn update_ime_cursor_area(&self, window: &Window) {
+        let (Some(editable), Some(rect)) =
+            (&self.editable, self.marked_text_rect.or(self.caret_rect))
+        else {
+            return;
+        };
+        let rect = editable.transform_rect(rect);
+        // The rect is in Flutter's logical coordinates, which are rotated
+        // like the pointer coordinates.
+        let scale_factor = window.scale_factor();
+        let window_size = window.inner_size();
+        let to_window = |x: f64, y: f64| {
+            let position = PhysicalPosition::new(x * scale_factor, y * scale_factor);
+            self.rotation
+                .to_window(position, window_size)
+                .to_logical::<f64>(scale_factor)
+        };
+        let corner = to_window(rect.x, rect.y);
+        let opposite_corner = to_window(rect.x + rect.width, rect.y + rect.height);
+        window.set_ime_cursor_area(
+            LogicalPosition::new(
+                corner.x.min(opposite_corner.x),
+                corner.y.min(opposite_corner.y),
+            ),
+            LogicalSize::new(
+                (opposite_corner.x - corner.x).abs(),
+                (opposite_corner.y - corner.y).abs(),
+            ),
+        );
+    }
+
+    /// Keeps the IME candidate window in place when the window is resized
+    /// or Flutter's output is rotated.
+    pub(super) fn metrics_changed(&mut self, rotation: Rotation, window: &Window) {
+        self.rotation = rotation;
+        self.update_ime_cursor_area(window);
+    }
+
+    pub(super) fn handle_textinput_message(&mut self, textinput: TextInput, window: &Window) {
         match textinput {
             TextInput::SetClient(client_id, parameters) => {
                 self.client = Some(client_id);
+                self.marked_text_rect = None;
+                self.undo_history.clear();
+                self.framework_undo_state = Default::default();
                 self.input_action = parameters.input_action;
+                self.input_type = parameters.input_type;
+                self.obscure_text = parameters.obscure_text;
+                self.enable_delta_model = parameters.enable_delta_model;
+                let fields = parameters.fields.into_iter().flatten();
+                for configuration in parameters
+                    .autofill
+                    .into_iter()
+                    .chain(fields.filter_map(|field| field.autofill))
+                {
+                    self.autofill_context
+                        .insert(configuration.unique_identifier.clone(), configuration);
+                }
                 log::debug!("Setting keyboard client to {:?}", client_id);
             }
             TextInput::ClearClient => {
                 self.client = None;
+                self.marked_text_rect = None;
+                self.undo_history.clear();
                 log::debug!("Setting keyboard client to None");
             }
             TextInput::SetEditingState(state) => {
                 log::debug!("set editing state: {:#?}", state);
+                if state.text != self.editing_state.text {
+                    // The embedder's history doesn't apply to text changed
+                    // by the framework.
+                    self.undo_history.clear();
+                }
+                self.framework_editing_state = state.clone();
                 self.editing_state = state;
+                self.update_primary_selection();
+            }
+            TextInput::Show => {
+                window.set_ime_allowed(true);
+                self.update_ime_cursor_area(window);
+            }
+            TextInput::Hide => {
+                self.marked_text_rect = None;
+                window.set_ime_allowed(false);
+            }
+            TextInput::SetEditableSizeAndTransform(editable) => {
+                self.editable = Some(editable);
+                self.update_ime_cursor_area(window);
+            }
+            TextInput::SetMarkedTextRect(rect) => {
+                self.marked_text_rect = Some(rect);
+                self.update_ime_cursor_area(window);
+            }
+            TextInput::SetCaretRect(rect) => {
+                self.caret_rect = Some(rect);
+                self.update_ime_cursor_area(window);
+            }
+            TextInput::SetStyle(style) => {
+                // winit has no API to style the preedit text.
+                log::debug!("Ignoring text style {style:?}");
+            }
+            TextInput::RequestAutofill => {
+                log::debug!("Autofill requested, but there is no autofill service");
+            }
+            TextInput::FinishAutofillContext(should_save) => {
+                // There's no autofill service to save the fields to, so the
+                // committed group is only logged.
+                for (unique_identifier, configuration) in self.autofill_context.drain() {
+                    if should_save {
+                        log::debug!(
+                            "Not saving autofill field {unique_identifier} ({:?}), there is no autofill service",
+                            configuration.hints
+                        );
+                    }
+                }
             }
-            other => {
-                log::warn!("Unhandled TextInput message: {:#?}", other);
+            TextInput::SendAppPrivateCommand(command) => {
+                log::debug!("Ignoring app private command {command:?}");
             }
         }
     }
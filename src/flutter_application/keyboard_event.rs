@@ -1,4 +1,31 @@
 use serde::{Deserialize, Serialize};
+use winit::keyboard::ModifiersState;
+
+// GdkModifierType bits, as interpreted by the framework's GTK key helper.
+const GTK_MODIFIER_SHIFT: u64 = 1 << 0;
+const GTK_MODIFIER_CONTROL: u64 = 1 << 2;
+const GTK_MODIFIER_MOD1: u64 = 1 << 3;
+const GTK_MODIFIER_SUPER: u64 = 1 << 26;
+const GTK_MODIFIER_META: u64 = 1 << 28;
+
+/// Converts winit's modifiers to the GTK modifier mask.
+pub(super) fn gtk_modifiers(modifiers: ModifiersState) -> u64 {
+    let mut mask = 0;
+    if modifiers.shift_key() {
+        mask |= GTK_MODIFIER_SHIFT;
+    }
+    if modifiers.control_key() {
+        mask |= GTK_MODIFIER_CONTROL;
+    }
+    if modifiers.alt_key() {
+        mask |= GTK_MODIFIER_MOD1;
+    }
+    // GDK sets both for the Super key, the framework only reads meta.
+    if modifiers.super_key() {
+        mask |= GTK_MODIFIER_SUPER | GTK_MODIFIER_META;
+    }
+    mask
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 #[serde(rename_all = "lowercase")]
@@ -15,7 +42,11 @@ pub(super) enum FlutterKeyboardEventType {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase", tag = "keymap")]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "keymap"
+)]
 pub(super) enum FlutterKeyboardEvent {
     Android {
         r#type: FlutterKeyboardEventType,
@@ -194,7 +225,8 @@ pub(super) enum FlutterKeyboardEvent {
         /// logical keys in a way that can not be derived from per-key information.
         ///
         /// This is not part of the native GTK key event.
-        specified_logical_key: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        specified_logical_key: Option<u64>,
     },
     Windows {
         r#type: FlutterKeyboardEventType,
@@ -256,3 +288,9 @@ pub(super) enum FlutterKeyboardEvent {
         key_code: u64,
     },
 }
+
+/// The framework's reply to a message on `flutter/keyevent`.
+#[derive(Debug, Deserialize)]
+pub(super) struct FlutterKeyboardEventResponse {
+    pub handled: bool,
+}
@@ -1,10 +1,81 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub(super) enum LifecycleState {
+    /// The window is visible and has input focus.
+    #[serde(rename = "AppLifecycleState.resumed")]
     Resumed,
+    /// The window is visible but doesn't have input focus.
+    #[serde(rename = "AppLifecycleState.inactive")]
     Inactive,
+    /// The window is minimized or fully covered by other windows.
+    #[serde(rename = "AppLifecycleState.hidden")]
+    Hidden,
+    /// The application was suspended by the system.
+    #[serde(rename = "AppLifecycleState.paused")]
     Paused,
+    /// The application is shutting down.
+    #[serde(rename = "AppLifecycleState.detached")]
     Detached,
 }
+
+/// Derives the lifecycle state from what is known about the window and the
+/// application.
+#[derive(Debug, Default)]
+pub(super) struct Lifecycle {
+    focused: bool,
+    occluded: bool,
+    minimized: bool,
+    suspended: bool,
+    detached: bool,
+    /// The state last sent to the framework.
+    state: Option<LifecycleState>,
+}
+
+impl Lifecycle {
+    pub(super) fn set_focused(&mut self, focused: bool) -> Option<LifecycleState> {
+        self.focused = focused;
+        self.update()
+    }
+
+    pub(super) fn set_occluded(&mut self, occluded: bool) -> Option<LifecycleState> {
+        self.occluded = occluded;
+        self.update()
+    }
+
+    pub(super) fn set_minimized(&mut self, minimized: bool) -> Option<LifecycleState> {
+        self.minimized = minimized;
+        self.update()
+    }
+
+    pub(super) fn set_suspended(&mut self, suspended: bool) -> Option<LifecycleState> {
+        self.suspended = suspended;
+        self.update()
+    }
+
+    pub(super) fn detach(&mut self) -> Option<LifecycleState> {
+        self.detached = true;
+        self.update()
+    }
+
+    /// Returns the new state if it changed. The framework fills in the
+    /// states in between itself, e.g. when going from resumed to hidden.
+    fn update(&mut self) -> Option<LifecycleState> {
+        let state = if self.detached {
+            LifecycleState::Detached
+        } else if self.suspended {
+            LifecycleState::Paused
+        } else if self.occluded || self.minimized {
+            LifecycleState::Hidden
+        } else if self.focused {
+            LifecycleState::Resumed
+        } else {
+            LifecycleState::Inactive
+        };
+        if self.state == Some(state) {
+            return None;
+        }
+        self.state = Some(state);
+        Some(state)
+    }
+}
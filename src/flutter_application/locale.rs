@@ -0,0 +1,197 @@
+use std::{
+    ffi::{CStr, CString},
+    mem::size_of,
+    os::raw::c_char,
+    ptr::null,
+    sync::Mutex,
+};
+
+use crate::flutter_bindings::{FlutterEngine, FlutterEngineUpdateLocales, FlutterLocale};
+
+use super::FlutterApplication;
+
+/// The user's locales in order of preference, for
+/// [`compute_platform_resolved_locale_callback`], which has no user data.
+static PREFERRED_LOCALES: Mutex<Vec<Locale>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Locale {
+    language: String,
+    country: Option<String>,
+    script: Option<String>,
+    variant: Option<String>,
+}
+
+impl Locale {
+    /// Parses a POSIX locale like `sr_RS.UTF-8@latin`, or a language tag like
+    /// `zh-Hant-TW`. The neutral `C` and `POSIX` locales are skipped.
+    pub(super) fn parse(locale: &str) -> Option<Self> {
+        let (locale, variant) = match locale.split_once('@') {
+            Some((locale, modifier)) => (locale, Some(modifier.to_owned())),
+            None => (locale, None),
+        };
+        let locale = locale.split('.').next().unwrap_or_default();
+        if locale.is_empty() || locale == "C" || locale == "POSIX" {
+            return None;
+        }
+        let mut parts = locale.split(['_', '-']);
+        let language = parts.next()?.to_lowercase();
+        let mut script = None;
+        let mut country = None;
+        for part in parts {
+            if part.len() == 4 && script.is_none() && country.is_none() {
+                script = Some(part.to_owned());
+            } else if country.is_none() {
+                country = Some(part.to_uppercase());
+            }
+        }
+        Some(Self {
+            language,
+            country,
+            script,
+            variant,
+        })
+    }
+
+    /// The locales from `LANGUAGE`, `LC_ALL`, `LC_MESSAGES` and `LANG`, in
+    /// the order gettext uses them.
+    pub(super) fn from_environment() -> Vec<Self> {
+        let var = |name| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let primary = var("LC_ALL")
+            .or_else(|| var("LC_MESSAGES"))
+            .or_else(|| var("LANG"));
+        let mut locales = vec![];
+        // LANGUAGE is ignored if the primary locale is the neutral one.
+        if primary.as_deref().and_then(Self::parse).is_some() {
+            if let Some(language) = var("LANGUAGE") {
+                locales.extend(language.split(':').filter_map(Self::parse));
+            }
+        }
+        locales.extend(primary.as_deref().and_then(Self::parse));
+        let mut unique = Vec::with_capacity(locales.len());
+        for locale in locales {
+            if !unique.contains(&locale) {
+                unique.push(locale);
+            }
+        }
+        unique
+    }
+
+    /// Parses a colon separated list like `de_AT:en_US`.
+    pub(super) fn parse_list(locales: &str) -> Vec<Self> {
+        locales.split(':').filter_map(Self::parse).collect()
+    }
+
+    fn matches(&self, locale: &FlutterLocale) -> (bool, bool, bool) {
+        // The engine passes empty strings for missing codes.
+        let code = |code: *const c_char| {
+            (!code.is_null())
+                .then(|| unsafe { CStr::from_ptr(code) }.to_string_lossy())
+                .filter(|code| !code.is_empty())
+        };
+        (
+            code(locale.language_code).as_deref() == Some(self.language.as_str()),
+            code(locale.country_code).as_deref() == self.country.as_deref(),
+            code(locale.script_code).as_deref() == self.script.as_deref(),
+        )
+    }
+}
+
+/// Reports the locales to the engine, the first is the preferred one.
+pub(super) fn update_locales(engine: FlutterEngine, locales: Vec<Locale>) {
+    log::info!("Locales: {locales:?}");
+    // Set first, the framework might resolve its locale right away.
+    *PREFERRED_LOCALES.lock().unwrap() = locales.clone();
+    let c_string = |code: &Option<String>| code.as_deref().map(|code| CString::new(code).unwrap());
+    let codes = locales
+        .iter()
+        .map(|locale| {
+            (
+                CString::new(locale.language.as_str()).unwrap(),
+                c_string(&locale.country),
+                c_string(&locale.script),
+                c_string(&locale.variant),
+            )
+        })
+        .collect::<Vec<_>>();
+    let c_ptr = |code: &Option<CString>| code.as_ref().map_or(null(), |code| code.as_ptr());
+    let flutter_locales = codes
+        .iter()
+        .map(|(language, country, script, variant)| FlutterLocale {
+            struct_size: size_of::<FlutterLocale>() as _,
+            language_code: language.as_ptr(),
+            country_code: c_ptr(country),
+            script_code: c_ptr(script),
+            variant_code: c_ptr(variant),
+        })
+        .collect::<Vec<_>>();
+    let mut flutter_locale_ptrs = flutter_locales
+        .iter()
+        .map(|locale| locale as *const FlutterLocale)
+        .collect::<Vec<_>>();
+    FlutterApplication::unwrap_result(unsafe {
+        FlutterEngineUpdateLocales(
+            engine,
+            flutter_locale_ptrs.as_mut_ptr(),
+            flutter_locale_ptrs.len(),
+        )
+    });
+}
+
+/// Picks the supported locale that best matches the user's preferences,
+/// trying each preferred locale with its country and script, then with its
+/// country, then by language alone.
+pub(super) extern "C" fn compute_platform_resolved_locale_callback(
+    supported_locales: *mut *const FlutterLocale,
+    count: usize,
+) -> *const FlutterLocale {
+    if supported_locales.is_null() {
+        return null();
+    }
+    let supported_locales = unsafe { std::slice::from_raw_parts(supported_locales, count) };
+    let preferred_locales = PREFERRED_LOCALES.lock().unwrap();
+    for preferred in preferred_locales.iter() {
+        let matches = supported_locales
+            .iter()
+            .map(|&supported| (supported, preferred.matches(unsafe { &*supported })))
+            .collect::<Vec<_>>();
+        for is_match in [
+            |(language, country, script)| language && country && script,
+            |(language, country, _)| language && country,
+            |(language, _, _)| language,
+        ] {
+            if let Some(&(supported, _)) = matches.iter().find(|(_, m)| is_match(*m)) {
+                return supported;
+            }
+        }
+    }
+    null()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_locales_with_empty_codes() {
+        let (en, us, empty) = (c"en", c"US", c"");
+        let flutter_locale = |country: &CStr| FlutterLocale {
+            struct_size: size_of::<FlutterLocale>() as _,
+            language_code: en.as_ptr(),
+            country_code: country.as_ptr(),
+            script_code: empty.as_ptr(),
+            variant_code: empty.as_ptr(),
+        };
+        let supported_locales = [flutter_locale(us), flutter_locale(empty)];
+        let mut supported_locale_ptrs = supported_locales
+            .iter()
+            .map(|locale| locale as *const FlutterLocale)
+            .collect::<Vec<_>>();
+        *PREFERRED_LOCALES.lock().unwrap() = vec![Locale::parse("en").unwrap()];
+        let resolved = compute_platform_resolved_locale_callback(
+            supported_locale_ptrs.as_mut_ptr(),
+            supported_locale_ptrs.len(),
+        );
+        assert_eq!(resolved, &supported_locales[1] as *const FlutterLocale);
+    }
+}
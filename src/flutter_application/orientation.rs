@@ -0,0 +1,87 @@
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+use super::platform::DeviceOrientation;
+
+/// How far Flutter's output is rotated clockwise to fit the preferred
+/// orientation on a screen that is in landscape orientation by default,
+/// e.g. a monitor mounted upright in a kiosk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) enum Rotation {
+    #[default]
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+impl Rotation {
+    /// The rotation for the first preferred orientation, an empty list lets
+    /// the system decide.
+    pub(super) fn from_preferred_orientations(orientations: &[DeviceOrientation]) -> Self {
+        match orientations.first() {
+            None | Some(DeviceOrientation::LandscapeLeft) => Rotation::None,
+            // The device is turned counterclockwise, so the output has to be
+            // turned clockwise to stay upright.
+            Some(DeviceOrientation::PortraitDown) => Rotation::Clockwise90,
+            Some(DeviceOrientation::LandscapeRight) => Rotation::Clockwise180,
+            Some(DeviceOrientation::PortraitUp) => Rotation::Clockwise270,
+        }
+    }
+
+    pub(super) fn swaps_axes(self) -> bool {
+        matches!(self, Rotation::Clockwise90 | Rotation::Clockwise270)
+    }
+
+    /// The size Flutter renders at to fill a window of `window_size`.
+    pub(super) fn flutter_size(self, window_size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+        if self.swaps_axes() {
+            PhysicalSize::new(window_size.height, window_size.width)
+        } else {
+            window_size
+        }
+    }
+
+    /// Maps a point in Flutter's coordinates to the window.
+    pub(super) fn to_window(
+        self,
+        position: PhysicalPosition<f64>,
+        window_size: PhysicalSize<u32>,
+    ) -> PhysicalPosition<f64> {
+        let (width, height) = (window_size.width as f64, window_size.height as f64);
+        let PhysicalPosition { x, y } = position;
+        match self {
+            Rotation::None => position,
+            Rotation::Clockwise90 => PhysicalPosition::new(width - y, x),
+            Rotation::Clockwise180 => PhysicalPosition::new(width - x, height - y),
+            Rotation::Clockwise270 => PhysicalPosition::new(y, height - x),
+        }
+    }
+
+    /// Maps a point in the window to Flutter's coordinates.
+    pub(super) fn to_flutter(
+        self,
+        position: PhysicalPosition<f64>,
+        window_size: PhysicalSize<u32>,
+    ) -> PhysicalPosition<f64> {
+        let (width, height) = (window_size.width as f64, window_size.height as f64);
+        let PhysicalPosition { x, y } = position;
+        match self {
+            Rotation::None => position,
+            Rotation::Clockwise90 => PhysicalPosition::new(y, width - x),
+            Rotation::Clockwise180 => PhysicalPosition::new(width - x, height - y),
+            Rotation::Clockwise270 => PhysicalPosition::new(height - y, x),
+        }
+    }
+
+    /// Maps a distance in the window, e.g. a scroll delta, to Flutter's
+    /// coordinates.
+    pub(super) fn delta_to_flutter(self, delta: (f64, f64)) -> (f64, f64) {
+        let (x, y) = delta;
+        match self {
+            Rotation::None => (x, y),
+            Rotation::Clockwise90 => (y, -x),
+            Rotation::Clockwise180 => (-x, -y),
+            Rotation::Clockwise270 => (-y, x),
+        }
+    }
+}
@@ -1,115 +1,387 @@
+use std::{
+    ffi::CString,
+    mem::size_of,
+    os::raw::c_void,
+    ptr::null_mut,
+    time::{Duration, Instant},
+};
+
 use serde::{Deserialize, Serialize};
-use winit::window::{Fullscreen, UserAttentionType};
+use winit::{
+    event_loop::EventLoopProxy,
+    window::{Fullscreen, Theme, Window},
+};
 
-use crate::flutter_bindings::FlutterEngine;
+use crate::flutter_bindings::{
+    FlutterEngine, FlutterEngineSendPlatformMessage, FlutterPlatformMessage,
+    FlutterPlatformMessageCreateResponseHandle, FlutterPlatformMessageReleaseResponseHandle,
+};
 
-use super::FlutterApplication;
+use super::{
+    clipboard::{Clipboard, ClipboardError, ClipboardImage},
+    feedback::{HapticFeedbackType, SystemSoundType},
+    orientation::Rotation,
+    FlutterApplication, FlutterApplicationCallback, FLUTTER_PLATFORM_CHANNEL,
+};
 
 pub(super) struct Platform;
 
+/// How close to the top of the window a press reveals the system overlays
+/// in [SystemUiMode::Immersive] and [SystemUiMode::ImmersiveSticky], in
+/// logical pixels.
+const IMMERSIVE_EDGE_SIZE: f64 = 8.0;
+
+/// How long [SystemUiMode::ImmersiveSticky] shows the overlays after they
+/// were revealed.
+pub(super) const STICKY_REVEAL_DURATION: Duration = Duration::from_secs(3);
+
+/// The state set through `SystemChrome`. On desktops, the window's
+/// decorations and the desktop's panels are the system overlays, so hiding
+/// them means going fullscreen.
+#[derive(Default)]
+pub(super) struct SystemChrome {
+    mode: Option<SystemUiMode>,
+    /// The overlays enabled in [SystemUiMode::Manual], all by default.
+    overlays: Option<Vec<SystemUiOverlay>>,
+    /// Whether the framework wants `SystemChrome.systemUIChange`.
+    change_listener: bool,
+    fullscreen: bool,
+    /// When the overlays were last revealed in
+    /// [SystemUiMode::ImmersiveSticky], until they are hidden again.
+    sticky_revealed_at: Option<Instant>,
+}
+
+/// How a press revealed the system overlays.
+pub(super) enum Reveal {
+    /// Until the application hides them again, the press is consumed.
+    UntilHidden,
+    /// For [STICKY_REVEAL_DURATION], the press is passed on to the
+    /// application.
+    Transient,
+}
+
+impl SystemChrome {
+    fn apply(&mut self, window: &Window) {
+        // Changes the overlays for good, the framework is told about it.
+        self.sticky_revealed_at = None;
+        let fullscreen = match self.mode {
+            None | Some(SystemUiMode::EdgeToEdge) => false,
+            Some(SystemUiMode::Manual) => self
+                .overlays
+                .as_ref()
+                .is_some_and(|overlays| overlays.is_empty()),
+            Some(
+                SystemUiMode::LeanBack | SystemUiMode::Immersive | SystemUiMode::ImmersiveSticky,
+            ) => true,
+        };
+        window.set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)));
+    }
+
+    /// Records whether the window is fullscreen, which the user might have
+    /// changed through the window manager as well. Returns whether the
+    /// overlays are visible if that changed and the framework listens.
+    pub(super) fn fullscreen_changed(&mut self, fullscreen: bool) -> Option<bool> {
+        if fullscreen == self.fullscreen {
+            return None;
+        }
+        self.fullscreen = fullscreen;
+        if self.sticky_revealed_at.is_some() {
+            // Revealing the overlays in [SystemUiMode::ImmersiveSticky] and
+            // hiding them again isn't a change of the mode.
+            if fullscreen {
+                self.sticky_revealed_at = None;
+            }
+            return None;
+        }
+        self.change_listener.then_some(!fullscreen)
+    }
+
+    /// Reveals the overlays on a press in the fullscreen modes that do so,
+    /// `position` is in logical pixels.
+    pub(super) fn pressed(&mut self, window: &Window, position: (f64, f64)) -> Option<Reveal> {
+        if !self.fullscreen {
+            return None;
+        }
+        let at_edge = position.1 < IMMERSIVE_EDGE_SIZE;
+        let reveal = match self.mode {
+            Some(SystemUiMode::LeanBack) => Reveal::UntilHidden,
+            Some(SystemUiMode::Immersive) if at_edge => Reveal::UntilHidden,
+            Some(SystemUiMode::ImmersiveSticky) if at_edge => {
+                self.sticky_revealed_at = Some(Instant::now());
+                Reveal::Transient
+            }
+            // The press is meant for the application.
+            _ => return None,
+        };
+        window.set_fullscreen(None);
+        Some(reveal)
+    }
+
+    /// Hides the overlays again once [STICKY_REVEAL_DURATION] passed since
+    /// they were last revealed, unless the mode changed in the meantime.
+    pub(super) fn hide_transient(&mut self, window: &Window) {
+        let expired = self
+            .sticky_revealed_at
+            .is_some_and(|revealed_at| revealed_at.elapsed() >= STICKY_REVEAL_DURATION);
+        if !expired {
+            return;
+        }
+        if self.fullscreen {
+            self.sticky_revealed_at = None;
+        } else if self.mode == Some(SystemUiMode::ImmersiveSticky) {
+            // The reveal ends once the window is fullscreen again.
+            window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+    }
+}
+
 impl Platform {
     pub(super) fn handle_message(
-        _engine: FlutterEngine,
+        engine: FlutterEngine,
         message: PlatformMessage,
-        application: &FlutterApplication,
+        application: &mut FlutterApplication,
     ) -> Option<Vec<u8>> {
         log::debug!("Platform message: {message:?}");
         match message {
             PlatformMessage::SystemChromeSetApplicationSwitcherDescription { label, .. } => {
                 application.window.set_title(&label);
             }
-            PlatformMessage::ClipboardSetData { text } => {
-                application
-                    .clipboard
-                    .lock()
-                    .unwrap()
-                    .set_text(text)
-                    .expect("Failed setting clipboard");
-            }
-            PlatformMessage::ClipboardGetData(_) => {
-                let text = application
-                    .clipboard
-                    .lock()
-                    .unwrap()
-                    .get_text()
-                    .expect("Failed reading clipboard");
+            PlatformMessage::ClipboardSetData(data) => {
                 return Some(
-                    serde_json::to_vec(&serde_json::json!({
-                        "text": text,
-                    }))
-                    .unwrap(),
+                    match Self::set_clipboard_data(&application.clipboard, data) {
+                        Ok(()) => Self::success(serde_json::Value::Null),
+                        Err(err) => Self::error("Clipboard error", err),
+                    },
                 );
             }
-            PlatformMessage::ClipboardHasStrings(_) => {
-                let has_strings = application.clipboard.lock().unwrap().get_text().is_ok();
+            PlatformMessage::ClipboardGetData(format) => {
+                // Flutter expects `null` when there's nothing to paste.
                 return Some(
-                    serde_json::to_vec(&serde_json::json!({
-                        "value": has_strings,
-                    }))
-                    .unwrap(),
+                    match Self::get_clipboard_data(&application.clipboard, format) {
+                        Ok(data) => Self::success(data),
+                        Err(ClipboardError::ContentNotAvailable) => {
+                            Self::success(serde_json::Value::Null)
+                        }
+                        Err(err) => Self::error("Clipboard error", err),
+                    },
                 );
             }
-            PlatformMessage::HapticFeedbackVibrate(feedback_type) => match feedback_type {
-                HapticFeedbackType::LightImpact => {}
-                HapticFeedbackType::MediumImpact => application
-                    .window
-                    .request_user_attention(Some(UserAttentionType::Informational)),
-                HapticFeedbackType::HeavyImpact => application
-                    .window
-                    .request_user_attention(Some(UserAttentionType::Critical)),
-                HapticFeedbackType::SelectionClick => {}
-            },
-            PlatformMessage::SystemSoundPlay(_) => {
-                application
-                    .window
-                    .request_user_attention(Some(UserAttentionType::Critical));
+            PlatformMessage::ClipboardHasStrings(_) => {
+                let has_strings = application
+                    .clipboard
+                    .text()
+                    .map(|text| !text.is_empty())
+                    .unwrap_or(false);
+                return Some(Self::success(serde_json::json!({
+                    "value": has_strings,
+                })));
+            }
+            PlatformMessage::HapticFeedbackVibrate(feedback_type) => {
+                (application.haptic_handler)(feedback_type.unwrap_or(HapticFeedbackType::Vibrate));
+            }
+            PlatformMessage::SystemSoundPlay(sound) => {
+                application.sound_backend.play(sound);
             }
             PlatformMessage::SystemNavigatorPop => {
-                application
-                    .user_data
-                    .event_loop_proxy
-                    .lock()
-                    .unwrap()
-                    .send_event(Box::new(|_| true))
-                    .ok()
-                    .unwrap();
+                application.request_exit();
+            }
+            PlatformMessage::SystemInitializationComplete => {
+                application.app_initialization_complete = true;
+            }
+            PlatformMessage::SystemExitApplication(ExitApplication { r#type }) => {
+                let response = match r#type {
+                    AppExitType::Required => {
+                        Self::exit(&application.user_data.event_loop_proxy.lock().unwrap());
+                        AppExitResponse::Exit
+                    }
+                    // Like the other desktop embedders, this asks the
+                    // framework in a separate request and reports the exit as
+                    // cancelled for now.
+                    AppExitType::Cancelable => {
+                        Self::request_app_exit(
+                            engine,
+                            application
+                                .user_data
+                                .event_loop_proxy
+                                .lock()
+                                .unwrap()
+                                .clone(),
+                        );
+                        AppExitResponse::Cancel
+                    }
+                };
+                return Some(Self::success(ExitResponse { response }));
+            }
+            PlatformMessage::SystemChromeSetPreferredOrientations(orientations) => {
+                application.set_rotation(Rotation::from_preferred_orientations(&orientations));
+            }
+            PlatformMessage::SystemChromeSetEnabledSystemUIOverlayStyle(style) => {
+                application.window.set_theme(style.theme());
+            }
+            PlatformMessage::SystemChromeSetEnabledSystemUIOverlays(overlays) => {
+                application.system_chrome.mode = Some(SystemUiMode::Manual);
+                application.system_chrome.overlays = Some(overlays);
+                application.system_chrome.apply(application.window);
+            }
+            PlatformMessage::SystemChromeRestoreSystemUIOverlays => {
+                application.system_chrome.apply(application.window);
             }
             PlatformMessage::SystemChromeSetEnabledSystemUIMode(mode) => {
-                if mode == SystemUiMode::Manual {
-                    application.window.set_fullscreen(None);
-                } else {
-                    application
-                        .window
-                        .set_fullscreen(Some(Fullscreen::Borderless(None)));
-                }
+                application.system_chrome.mode = Some(mode);
+                application.system_chrome.apply(application.window);
+            }
+            PlatformMessage::SystemChromeSetSystemUIChangeListener => {
+                application.system_chrome.change_listener = true;
             }
             _ => {}
         }
         None
     }
+
+    /// Asks the framework whether the application may exit, and exits if it
+    /// agrees.
+    pub(super) fn request_app_exit(
+        engine: FlutterEngine,
+        event_loop_proxy: EventLoopProxy<FlutterApplicationCallback>,
+    ) {
+        let channel = CString::new(FLUTTER_PLATFORM_CHANNEL).unwrap();
+        let message = serde_json::to_vec(&PlatformMessage::SystemRequestAppExit(ExitApplication {
+            r#type: AppExitType::Cancelable,
+        }))
+        .unwrap();
+        let mut response_handle = null_mut();
+        FlutterApplication::unwrap_result(unsafe {
+            FlutterPlatformMessageCreateResponseHandle(
+                engine,
+                Some(Self::request_app_exit_response),
+                Box::into_raw(Box::new(event_loop_proxy)) as *mut c_void,
+                &mut response_handle,
+            )
+        });
+        FlutterApplication::unwrap_result(unsafe {
+            FlutterEngineSendPlatformMessage(
+                engine,
+                &FlutterPlatformMessage {
+                    struct_size: size_of::<FlutterPlatformMessage>() as _,
+                    channel: channel.as_ptr(),
+                    message: message.as_ptr(),
+                    message_size: message.len() as _,
+                    response_handle,
+                },
+            )
+        });
+        FlutterApplication::unwrap_result(unsafe {
+            FlutterPlatformMessageReleaseResponseHandle(engine, response_handle)
+        });
+    }
+
+    extern "C" fn request_app_exit_response(data: *const u8, size: usize, user_data: *mut c_void) {
+        let event_loop_proxy =
+            unsafe { Box::from_raw(user_data as *mut EventLoopProxy<FlutterApplicationCallback>) };
+        // Without a reply nobody is interested in exit requests.
+        let response = if data.is_null() || size == 0 {
+            AppExitResponse::Exit
+        } else {
+            let data = unsafe { std::slice::from_raw_parts(data, size) };
+            serde_json::from_slice::<(ExitResponse,)>(data)
+                .map(|(response,)| response.response)
+                .unwrap_or_else(|err| {
+                    log::error!(
+                        "Invalid System.requestAppExit response {:?}: {err}",
+                        std::str::from_utf8(data)
+                    );
+                    AppExitResponse::Exit
+                })
+        };
+        match response {
+            AppExitResponse::Exit => Self::exit(&event_loop_proxy),
+            AppExitResponse::Cancel => log::info!("Exit cancelled by the application"),
+        }
+    }
+
+    fn exit(event_loop_proxy: &EventLoopProxy<FlutterApplicationCallback>) {
+        event_loop_proxy
+            .send_event(Box::new(|_| true))
+            .ok()
+            .unwrap();
+    }
+
+    /// Places the data on the clipboard. Only one format is kept, images and
+    /// files take precedence over HTML, which takes precedence over text.
+    fn set_clipboard_data(
+        clipboard: &Clipboard,
+        data: ClipboardData,
+    ) -> Result<(), ClipboardError> {
+        if let Some(png) = data.image {
+            clipboard.set_image(&ClipboardImage::from_png(&png)?)
+        } else if let Some(uris) = data.uris {
+            clipboard.set_uri_list(&uris)
+        } else if let Some(html) = data.html {
+            clipboard.set_html(&html, data.text.as_deref())
+        } else if let Some(text) = data.text {
+            clipboard.set_text(&text)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn get_clipboard_data(
+        clipboard: &Clipboard,
+        format: ClipboardFormat,
+    ) -> Result<ClipboardData, ClipboardError> {
+        Ok(match format {
+            ClipboardFormat::TextPlain => ClipboardData {
+                text: Some(clipboard.text()?),
+                ..Default::default()
+            },
+            ClipboardFormat::TextHtml => ClipboardData {
+                html: Some(clipboard.html()?),
+                ..Default::default()
+            },
+            ClipboardFormat::ImagePng => ClipboardData {
+                image: Some(clipboard.image()?.to_png()?),
+                ..Default::default()
+            },
+            ClipboardFormat::TextUriList => ClipboardData {
+                uris: Some(clipboard.uri_list()?),
+                ..Default::default()
+            },
+        })
+    }
+
+    pub(super) fn success(result: impl Serialize) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!([result])).unwrap()
+    }
+
+    pub(super) fn error(code: &str, err: impl std::fmt::Display) -> Vec<u8> {
+        log::error!("{code}: {err}");
+        serde_json::to_vec(&serde_json::json!([code, err.to_string(), null])).unwrap()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "method", content = "args")]
 pub(super) enum PlatformMessage {
-    /// Places the data from the `text` entry of the argument.
+    /// Places the data from the `text` entry of the argument. The `html`,
+    /// `image` and `uris` entries are an extension of this embedder.
     #[serde(rename = "Clipboard.setData")]
-    ClipboardSetData { text: String },
+    ClipboardSetData(ClipboardData),
     /// Returns the data that has the format specified in
-    /// the argument, a [String], from the system clipboard.
+    /// the argument, a [String], from the system clipboard. Formats other
+    /// than `text/plain` are an extension of this embedder.
     #[serde(rename = "Clipboard.getData")]
     ClipboardGetData(ClipboardFormat),
     /// Should return `{"value":true}` iff the clipboard contains string data,
     /// otherwise `{"value":false}`.
     #[serde(rename = "Clipboard.hasStrings")]
     ClipboardHasStrings(ClipboardFormat),
-    /// Triggers a system-default haptic response.
+    /// Triggers a system-default haptic response, `null` for a plain
+    /// vibration.
     #[serde(rename = "HapticFeedback.vibrate")]
-    HapticFeedbackVibrate(HapticFeedbackType),
-    /// Triggers a system audio effect. The argument must
-    /// be a [String] describing the desired effect
+    HapticFeedbackVibrate(Option<HapticFeedbackType>),
+    /// Triggers a system audio effect.
     #[serde(rename = "SystemSound.play")]
-    SystemSoundPlay(String),
+    SystemSoundPlay(SystemSoundType),
     /// Informs the operating system of the desired orientation of the display.
     #[serde(rename = "SystemChrome.setPreferredOrientations")]
     SystemChromeSetPreferredOrientations(Vec<DeviceOrientation>),
@@ -149,6 +421,17 @@ pub(super) enum PlatformMessage {
     /// equivalent.
     #[serde(rename = "SystemNavigator.pop")]
     SystemNavigatorPop,
+    /// The framework is ready to handle `System.requestAppExit`.
+    #[serde(rename = "System.initializationComplete")]
+    SystemInitializationComplete,
+    /// Exits the application, or asks the framework first if the exit is
+    /// cancelable.
+    #[serde(rename = "System.exitApplication")]
+    SystemExitApplication(ExitApplication),
+    /// Outgoing. Asks the framework whether the application may exit,
+    /// answered with an [ExitResponse].
+    #[serde(rename = "System.requestAppExit")]
+    SystemRequestAppExit(ExitApplication),
     /// Undocumented but sent when a listener for the event below is registered
     #[serde(rename = "SystemChrome.setSystemUIChangeListener")]
     SystemChromeSetSystemUIChangeListener,
@@ -176,18 +459,54 @@ pub(super) enum PlatformMessage {
 pub(super) enum ClipboardFormat {
     #[serde(rename = "text/plain")]
     TextPlain,
+    #[serde(rename = "text/html")]
+    TextHtml,
+    #[serde(rename = "image/png")]
+    ImagePng,
+    #[serde(rename = "text/uri-list")]
+    TextUriList,
+}
+
+/// The argument of `Clipboard.setData` and the result of
+/// `Clipboard.getData`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub(super) struct ClipboardData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html: Option<String>,
+    /// PNG encoded, as a list of bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<Vec<u8>>,
+    /// `file://` URIs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uris: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(super) struct ExitApplication {
+    r#type: AppExitType,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub(super) enum AppExitType {
+    /// The framework may cancel the exit.
+    Cancelable,
+    /// The application exits no matter what.
+    Required,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(super) enum HapticFeedbackType {
-    #[serde(rename = "HapticFeedbackType.lightImpact")]
-    LightImpact,
-    #[serde(rename = "HapticFeedbackType.mediumImpact")]
-    MediumImpact,
-    #[serde(rename = "HapticFeedbackType.heavyImpact")]
-    HeavyImpact,
-    #[serde(rename = "HapticFeedbackType.selectionClick")]
-    SelectionClick,
+pub(super) struct ExitResponse {
+    response: AppExitResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub(super) enum AppExitResponse {
+    Exit,
+    Cancel,
 }
 
 /// Specifies a particular device orientation.
@@ -207,21 +526,24 @@ pub(super) enum HapticFeedbackType {
 ///
 /// Used by [SystemChrome.setPreferredOrientations].
 #[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
 pub(super) enum DeviceOrientation {
     /// If the device shows its boot logo in portrait, then the boot logo is shown
     /// in [portraitUp]. Otherwise, the device shows its boot logo in landscape
     /// and this orientation is obtained by rotating the device 90 degrees
     /// clockwise from its boot orientation.
+    #[serde(rename = "DeviceOrientation.portraitUp")]
     PortraitUp,
     /// The orientation that is 90 degrees clockwise from [PortraitUp].
     ///
     /// If the device shows its boot logo in landscape, then the boot logo is
     /// shown in [LandscapeLeft].
+    #[serde(rename = "DeviceOrientation.landscapeLeft")]
     LandscapeLeft,
     /// The orientation that is 180 degrees from [PortraitUp].
+    #[serde(rename = "DeviceOrientation.portraitDown")]
     PortraitDown,
     /// The orientation that is 90 degrees counterclockwise from [PortraitUp].
+    #[serde(rename = "DeviceOrientation.landscapeRight")]
     LandscapeRight,
 }
 
@@ -229,13 +551,14 @@ pub(super) enum DeviceOrientation {
 ///
 /// Used by [SystemChrome.setEnabledSystemUIOverlays].
 #[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
 pub(super) enum SystemUiOverlay {
     /// The status bar provided by the embedder on the top of the application
     /// surface, if any.
+    #[serde(rename = "SystemUiOverlay.top")]
     Top,
     /// The status bar provided by the embedder on the bottom of the application
     /// surface, if any.
+    #[serde(rename = "SystemUiOverlay.bottom")]
     Bottom,
 }
 
@@ -244,8 +567,7 @@ pub(super) enum SystemUiOverlay {
 /// These modes mimic Android-specific display setups.
 ///
 /// Used by [SystemChrome.setEnabledSystemUIMode].
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub(super) enum SystemUiMode {
     /// Fullscreen display with status and navigation bars presentable by tapping
     /// anywhere on the display.
@@ -263,6 +585,7 @@ pub(super) enum SystemUiMode {
     ///
     ///   * [SystemUiChangeCallback], used to listen and respond to the change in
     ///     system overlays.
+    #[serde(rename = "SystemUiMode.leanBack")]
     LeanBack,
 
     /// Fullscreen display with status and navigation bars presentable through a
@@ -282,6 +605,7 @@ pub(super) enum SystemUiMode {
     ///
     ///   * [SystemUiChangeCallback], used to listen and respond to the change in
     ///     system overlays.
+    #[serde(rename = "SystemUiMode.immersive")]
     Immersive,
 
     /// Fullscreen display with status and navigation bars presentable through a
@@ -300,6 +624,7 @@ pub(super) enum SystemUiMode {
     ///
     ///   * [SystemUiChangeCallback], used to listen and respond to the change in
     ///     system overlays.
+    #[serde(rename = "SystemUiMode.immersiveSticky")]
     ImmersiveSticky,
 
     /// Fullscreen display with status and navigation elements rendered over the
@@ -318,6 +643,7 @@ pub(super) enum SystemUiMode {
     ///
     ///   * [SystemUiOverlayStyle], can be used to configure transparent status and
     ///     navigation bars with or without a contrast scrim.
+    #[serde(rename = "SystemUiMode.edgeToEdge")]
     EdgeToEdge,
 
     /// Declares manually configured [SystemUiOverlay]s.
@@ -335,17 +661,43 @@ pub(super) enum SystemUiMode {
     ///
     /// Omitting both overlays will result in the same configuration as
     /// [SystemUiMode.leanBack].
+    #[serde(rename = "SystemUiMode.manual")]
     Manual,
 }
 
-/// Specifies a preference for the style of the system overlays.
+/// Specifies a preference for the style of the system overlays. Only the
+/// brightness is meaningful on desktops, the colors are ignored.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub(super) enum SystemUiOverlayStyle {
-    /// System overlays should be drawn with a light color. Intended for
-    /// applications with a dark background.
+pub(super) struct SystemUiOverlayStyle {
+    pub(super) system_navigation_bar_color: Option<u32>,
+    pub(super) system_navigation_bar_divider_color: Option<u32>,
+    pub(super) system_navigation_bar_icon_brightness: Option<Brightness>,
+    pub(super) system_navigation_bar_contrast_enforced: Option<bool>,
+    pub(super) status_bar_color: Option<u32>,
+    /// The brightness of the status bar itself, used on iOS.
+    pub(super) status_bar_brightness: Option<Brightness>,
+    /// The brightness of the status bar's icons, used on Android.
+    pub(super) status_bar_icon_brightness: Option<Brightness>,
+    pub(super) system_status_bar_contrast_enforced: Option<bool>,
+}
+
+impl SystemUiOverlayStyle {
+    /// The window theme that matches the style, `None` for the system's.
+    pub(super) fn theme(&self) -> Option<Theme> {
+        match (self.status_bar_icon_brightness, self.status_bar_brightness) {
+            // Light icons are meant for a dark application.
+            (Some(Brightness::Light), _) | (None, Some(Brightness::Dark)) => Some(Theme::Dark),
+            (Some(Brightness::Dark), _) | (None, Some(Brightness::Light)) => Some(Theme::Light),
+            (None, None) => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Brightness {
+    #[serde(rename = "Brightness.light")]
     Light,
-    /// System overlays should be drawn with a dark color. Intended for
-    /// applications with a light background.
+    #[serde(rename = "Brightness.dark")]
     Dark,
 }
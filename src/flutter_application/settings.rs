@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+use winit::window::Theme;
+
+/// The message on `flutter/settings`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Settings {
+    pub(super) text_scale_factor: f64,
+    pub(super) always_use_24_hour_format: bool,
+    pub(super) platform_brightness: PlatformBrightness,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(super) enum PlatformBrightness {
+    Light,
+    Dark,
+}
+
+impl From<Theme> for PlatformBrightness {
+    fn from(theme: Theme) -> Self {
+        match theme {
+            Theme::Light => PlatformBrightness::Light,
+            Theme::Dark => PlatformBrightness::Dark,
+        }
+    }
+}
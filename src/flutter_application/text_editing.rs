@@ -0,0 +1,202 @@
+use std::ops::Range;
+
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
+
+// Flutter counts offsets in a `TextEditingValue` in UTF-16 code units, so
+// everything here takes and returns those.
+
+pub(super) fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+/// The byte offset of a UTF-16 offset. Offsets inside of a surrogate pair
+/// are moved to the end of the character, offsets past the end of the text
+/// are clamped.
+pub(super) fn byte_offset(text: &str, offset: usize) -> usize {
+    let mut utf16_offset = 0;
+    for (byte_offset, c) in text.char_indices() {
+        if utf16_offset >= offset {
+            return byte_offset;
+        }
+        utf16_offset += c.len_utf16();
+    }
+    text.len()
+}
+
+pub(super) fn byte_range(text: &str, range: Range<usize>) -> Range<usize> {
+    byte_offset(text, range.start)..byte_offset(text, range.end)
+}
+
+fn utf16_offset(text: &str, byte_offset: usize) -> usize {
+    utf16_len(&text[..byte_offset])
+}
+
+/// The start of the grapheme cluster before `offset`.
+pub(super) fn previous_grapheme(text: &str, offset: usize) -> usize {
+    let mut cursor = GraphemeCursor::new(byte_offset(text, offset), text.len(), true);
+    match cursor.prev_boundary(text, 0) {
+        Ok(Some(boundary)) => utf16_offset(text, boundary),
+        _ => 0,
+    }
+}
+
+/// The end of the grapheme cluster after `offset`.
+pub(super) fn next_grapheme(text: &str, offset: usize) -> usize {
+    let mut cursor = GraphemeCursor::new(byte_offset(text, offset), text.len(), true);
+    match cursor.next_boundary(text, 0) {
+        Ok(Some(boundary)) => utf16_offset(text, boundary),
+        _ => utf16_len(text),
+    }
+}
+
+/// The start of the word before `offset`, skipping any whitespace and
+/// punctuation in between.
+pub(super) fn previous_word(text: &str, offset: usize) -> usize {
+    let byte_offset = byte_offset(text, offset);
+    text.unicode_word_indices()
+        .map(|(start, _)| start)
+        .take_while(|&start| start < byte_offset)
+        .last()
+        .map(|start| utf16_offset(text, start))
+        .unwrap_or(0)
+}
+
+/// The end of the word after `offset`, skipping any whitespace and
+/// punctuation in between.
+pub(super) fn next_word(text: &str, offset: usize) -> usize {
+    let byte_offset = byte_offset(text, offset);
+    text.unicode_word_indices()
+        .map(|(start, word)| start + word.len())
+        .find(|&end| end > byte_offset)
+        .map(|end| utf16_offset(text, end))
+        .unwrap_or_else(|| utf16_len(text))
+}
+
+/// The start of the line containing `offset`. Only explicit line breaks are
+/// known here, lines wrapped by the framework's layout are not.
+pub(super) fn line_start(text: &str, offset: usize) -> usize {
+    text[..byte_offset(text, offset)]
+        .rfind('\n')
+        .map(|newline| utf16_offset(text, newline + 1))
+        .unwrap_or(0)
+}
+
+/// The end of the line containing `offset`, before the line break.
+pub(super) fn line_end(text: &str, offset: usize) -> usize {
+    let byte_offset = byte_offset(text, offset);
+    text[byte_offset..]
+        .find('\n')
+        .map(|newline| utf16_offset(text, byte_offset + newline))
+        .unwrap_or_else(|| utf16_len(text))
+}
+
+/// The number of grapheme clusters between the start of the line and
+/// `offset`.
+pub(super) fn column(text: &str, offset: usize) -> usize {
+    let start = byte_offset(text, line_start(text, offset));
+    text[start..byte_offset(text, offset)]
+        .graphemes(true)
+        .count()
+}
+
+/// The offset of `column` in the line starting at `line_start`, or the end
+/// of the line if it is shorter.
+pub(super) fn offset_in_line(text: &str, line_start: usize, column: usize) -> usize {
+    let start = byte_offset(text, line_start);
+    let line = &text[start..byte_offset(text, line_end(text, line_start))];
+    line_start + utf16_len(&line.graphemes(true).take(column).collect::<String>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A family emoji, three people joined by zero width joiners.
+    const FAMILY: &str = "👩\u{200d}👩\u{200d}👧";
+    // "e" followed by a combining acute accent and a combining diaeresis.
+    const COMBINING: &str = "e\u{301}\u{308}";
+
+    #[test]
+    fn lengths_and_offsets() {
+        assert_eq!(utf16_len("abc"), 3);
+        assert_eq!(utf16_len("😀"), 2);
+        assert_eq!(utf16_len(FAMILY), 8);
+        assert_eq!(byte_offset("a😀b", 1), 1);
+        assert_eq!(byte_offset("a😀b", 3), 5);
+        assert_eq!(byte_range("a😀b", 1..3), 1..5);
+    }
+
+    #[test]
+    fn byte_offset_clamps() {
+        assert_eq!(byte_offset("abc", 10), 3);
+        assert_eq!(byte_offset("", 1), 0);
+        // Inside the surrogate pair of 😀 moves to the end of the character.
+        assert_eq!(byte_offset("a😀b", 2), 5);
+        assert_eq!(byte_range("a😀b", 2..10), 5..6);
+    }
+
+    #[test]
+    fn grapheme_stepping() {
+        let text = format!("a{FAMILY}{COMBINING}😀b");
+        let stops = [0, 1, 9, 12, 14, 15];
+        for pair in stops.windows(2) {
+            assert_eq!(next_grapheme(&text, pair[0]), pair[1]);
+            assert_eq!(previous_grapheme(&text, pair[1]), pair[0]);
+        }
+        assert_eq!(next_grapheme(&text, 15), 15);
+        assert_eq!(previous_grapheme(&text, 0), 0);
+        // From inside a cluster to its boundaries.
+        assert_eq!(next_grapheme(&text, 3), 9);
+        assert_eq!(previous_grapheme(&text, 3), 1);
+    }
+
+    #[test]
+    fn word_jumps() {
+        let text = "foo, bar!  baz";
+        assert_eq!(next_word(text, 0), 3);
+        assert_eq!(next_word(text, 3), 8);
+        assert_eq!(next_word(text, 8), 14);
+        assert_eq!(next_word(text, 14), 14);
+        assert_eq!(previous_word(text, 14), 11);
+        assert_eq!(previous_word(text, 11), 5);
+        assert_eq!(previous_word(text, 5), 0);
+        assert_eq!(previous_word(text, 0), 0);
+        // Offsets after non-BMP text are still UTF-16 offsets.
+        assert_eq!(next_word("😀 ab", 0), 5);
+        assert_eq!(previous_word("😀 ab", 5), 3);
+    }
+
+    #[test]
+    fn lines() {
+        let text = "ab\n😀c\n";
+        assert_eq!(line_start(text, 1), 0);
+        assert_eq!(line_end(text, 1), 2);
+        assert_eq!(line_start(text, 5), 3);
+        assert_eq!(line_end(text, 3), 6);
+        assert_eq!(line_start(text, 7), 7);
+        assert_eq!(line_end(text, 7), 7);
+        assert_eq!(column(text, 5), 1);
+        assert_eq!(offset_in_line(text, 3, 1), 5);
+        assert_eq!(offset_in_line(text, 0, 5), 2);
+    }
+
+    /// Removes `range` like the keyboard's text editing does.
+    fn remove(text: &str, range: Range<usize>) -> String {
+        let mut text = text.to_owned();
+        text.replace_range(byte_range(&text, range), "");
+        text
+    }
+
+    #[test]
+    fn backspace_and_delete_remove_whole_clusters() {
+        let text = format!("a{FAMILY}b");
+        let backspace = |offset| remove(&text, previous_grapheme(&text, offset)..offset);
+        let delete = |offset| remove(&text, offset..next_grapheme(&text, offset));
+        assert_eq!(backspace(9), "ab");
+        assert_eq!(delete(1), "ab");
+
+        let text = format!("{COMBINING}😀");
+        assert_eq!(remove(&text, previous_grapheme(&text, 5)..5), COMBINING);
+        assert_eq!(remove(&text, 0..next_grapheme(&text, 0)), "😀");
+    }
+}
@@ -73,6 +73,18 @@ pub(super) struct TextClientParameters {
     #[serde(rename = "enableIMEPersonalizedLearning")]
     pub(super) enable_ime_personalized_learning: bool,
     pub(super) enable_delta_model: bool,
+    pub(super) autofill: Option<AutofillConfiguration>,
+    /// The configurations of all text fields in the same autofill group.
+    pub(super) fields: Option<Vec<TextClientParameters>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct AutofillConfiguration {
+    pub(super) unique_identifier: String,
+    pub(super) hints: Vec<String>,
+    pub(super) editing_value: TextEditingValue,
+    pub(super) hint_text: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -104,6 +116,99 @@ pub(super) enum TextInput {
     /// be called at any time. See [TextInputConnection.close].
     #[serde(rename = "TextInput.hide")]
     Hide,
+    /// The size of the editable in its local coordinate system and the
+    /// transform from there to the view's coordinate system. See
+    /// [TextInputConnection.setEditableSizeAndTransform].
+    #[serde(rename = "TextInput.setEditableSizeAndTransform")]
+    SetEditableSizeAndTransform(EditableSizeAndTransform),
+    /// The rect of the composing text in the editable's local coordinate
+    /// system, used to position the IME candidate window. See
+    /// [TextInputConnection.setComposingRect].
+    #[serde(rename = "TextInput.setMarkedTextRect")]
+    SetMarkedTextRect(Rect),
+    /// The rect of the caret in the editable's local coordinate system. See
+    /// [TextInputConnection.setCaretRect].
+    #[serde(rename = "TextInput.setCaretRect")]
+    SetCaretRect(Rect),
+    /// The text style of the editable. See [TextInputConnection.setStyle].
+    #[serde(rename = "TextInput.setStyle")]
+    SetStyle(TextStyle),
+    /// Asks the platform's autofill service to fill the current autofill
+    /// group. See [TextInputConnection.requestAutofill].
+    #[serde(rename = "TextInput.requestAutofill")]
+    RequestAutofill,
+    /// Ends the current autofill context. The argument tells whether the
+    /// autofill service should save the user's input for future use. See
+    /// [TextInput.finishAutofillContext].
+    #[serde(rename = "TextInput.finishAutofillContext")]
+    FinishAutofillContext(bool),
+    /// Sends a private command to the input method. This is only meaningful
+    /// on Android. See [TextInputConnection.sendAppPrivateCommand].
+    #[serde(rename = "TextInput.sendAppPrivateCommand")]
+    SendAppPrivateCommand(AppPrivateCommand),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct TextStyle {
+    pub(super) font_family: Option<String>,
+    pub(super) font_size: Option<f64>,
+    /// The index into `FontWeight.values`, from w100 to w900.
+    pub(super) font_weight_index: Option<u8>,
+    /// The index into `TextAlign.values`.
+    pub(super) text_align_index: u8,
+    /// The index into `TextDirection.values`, 0 is rtl and 1 is ltr.
+    pub(super) text_direction_index: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(super) struct AppPrivateCommand {
+    pub(super) action: String,
+    pub(super) data: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(super) struct EditableSizeAndTransform {
+    pub(super) width: f64,
+    pub(super) height: f64,
+    /// A 4x4 matrix in column-major order.
+    pub(super) transform: Vec<f64>,
+}
+
+impl EditableSizeAndTransform {
+    /// Maps a point from the editable's coordinate system to the view's.
+    pub(super) fn transform_point(&self, x: f64, y: f64) -> (f64, f64) {
+        let m = &self.transform;
+        if m.len() != 16 {
+            return (x, y);
+        }
+        let w = m[3] * x + m[7] * y + m[15];
+        let w = if w == 0.0 { 1.0 } else { w };
+        (
+            (m[0] * x + m[4] * y + m[12]) / w,
+            (m[1] * x + m[5] * y + m[13]) / w,
+        )
+    }
+
+    /// Maps a rect from the editable's coordinate system to the view's.
+    pub(super) fn transform_rect(&self, rect: Rect) -> Rect {
+        let (left, top) = self.transform_point(rect.x, rect.y);
+        let (right, bottom) = self.transform_point(rect.x + rect.width, rect.y + rect.height);
+        Rect {
+            x: left.min(right),
+            y: top.min(bottom),
+            width: (right - left).abs(),
+            height: (bottom - top).abs(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub(super) struct Rect {
+    pub(super) x: f64,
+    pub(super) y: f64,
+    pub(super) width: f64,
+    pub(super) height: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -126,6 +231,82 @@ pub(super) struct TextEditingValue {
     pub(super) composing_extent: Option<i64>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(super) struct TextEditingDeltas {
+    pub(super) deltas: Vec<TextEditingDelta>,
+}
+
+/// A single change to a [TextEditingValue]: the range `delta_start` to
+/// `delta_end` of `old_text` is replaced by `delta_text`, both given in
+/// UTF-16 code units. A range of -1 to -1 means only the selection or
+/// composing region changed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct TextEditingDelta {
+    pub(super) old_text: String,
+    pub(super) delta_text: String,
+    pub(super) delta_start: i64,
+    pub(super) delta_end: i64,
+    pub(super) selection_base: Option<i64>,
+    pub(super) selection_extent: Option<i64>,
+    pub(super) selection_affinity: Option<TextAffinity>,
+    pub(super) selection_is_directional: Option<bool>,
+    pub(super) composing_base: Option<i64>,
+    pub(super) composing_extent: Option<i64>,
+}
+
+impl TextEditingDelta {
+    /// The delta that turns `old` into `new`. The changed text is found by
+    /// stripping the common prefix and suffix, so it's always a single
+    /// insertion, deletion or replacement.
+    pub(super) fn between(old: &TextEditingValue, new: &TextEditingValue) -> Self {
+        let (delta_start, delta_end, delta_text) = if old.text == new.text {
+            (-1, -1, String::new())
+        } else {
+            let prefix = old
+                .text
+                .chars()
+                .zip(new.text.chars())
+                .take_while(|(old, new)| old == new)
+                .map(|(c, _)| c.len_utf8())
+                .sum::<usize>();
+            let max_suffix = old.text.len().min(new.text.len()) - prefix;
+            let suffix = old.text[prefix..]
+                .chars()
+                .rev()
+                .zip(new.text[prefix..].chars().rev())
+                .take_while(|(old, new)| old == new)
+                .map(|(c, _)| c.len_utf8())
+                .scan(0, |len, c_len| {
+                    *len += c_len;
+                    Some(*len)
+                })
+                .take_while(|len| *len <= max_suffix)
+                .last()
+                .unwrap_or(0);
+            let utf16_len = |text: &str| text.encode_utf16().count() as i64;
+            let delta_start = utf16_len(&old.text[..prefix]);
+            (
+                delta_start,
+                delta_start + utf16_len(&old.text[prefix..old.text.len() - suffix]),
+                new.text[prefix..new.text.len() - suffix].to_owned(),
+            )
+        };
+        Self {
+            old_text: old.text.clone(),
+            delta_text,
+            delta_start,
+            delta_end,
+            selection_base: new.selection_base,
+            selection_extent: new.selection_extent,
+            selection_affinity: new.selection_affinity.clone(),
+            selection_is_directional: new.selection_is_directional,
+            composing_base: new.composing_base,
+            composing_extent: new.composing_extent,
+        }
+    }
+}
+
 /// An action the user has requested the text input control to perform.
 ///
 /// Each action represents a logical meaning, and also configures the soft
@@ -338,8 +519,12 @@ pub(super) enum TextInputClient {
     /// in the form expected by [TextEditingValue.fromJSON].
     #[serde(rename = "TextInputClient.updateEditingState")]
     UpdateEditingState(u64, TextEditingValue),
+    /// The user has changed the contents of
+    /// the text control, sent instead of `updateEditingState` if the client
+    /// enabled the delta model. The second argument is an object with a
+    /// `deltas` list, in the form expected by [TextEditingDelta.fromJSON].
     #[serde(rename = "TextInputClient.updateEditingStateWithDeltas")]
-    UpdateEditingWithDeltas(u64, serde_json::Map<String, serde_json::Value>),
+    UpdateEditingStateWithDeltas(u64, TextEditingDeltas),
     /// One or more text controls
     /// were autofilled by the platform's autofill service. The first argument
     /// (the client ID) is ignored, the second argument is a map of tags to
@@ -381,3 +566,127 @@ pub(super) enum TextInputClient {
     #[serde(rename = "TextInputClient.removeTextPlaceholder")]
     RemoveTextPlaceholder(u64),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(text: &str, selection: (i64, i64), composing: (i64, i64)) -> TextEditingValue {
+        TextEditingValue {
+            text: text.to_owned(),
+            selection_base: Some(selection.0),
+            selection_extent: Some(selection.1),
+            selection_affinity: Some(TextAffinity::Downstream),
+            selection_is_directional: Some(false),
+            composing_base: Some(composing.0),
+            composing_extent: Some(composing.1),
+        }
+    }
+
+    /// Applies the delta the way the framework's `TextEditingDelta` does, on
+    /// UTF-16 code units.
+    fn apply(delta: &TextEditingDelta) -> TextEditingValue {
+        let mut text = delta.old_text.encode_utf16().collect::<Vec<_>>();
+        if delta.delta_start >= 0 {
+            text.splice(
+                delta.delta_start as usize..delta.delta_end as usize,
+                delta.delta_text.encode_utf16(),
+            );
+        }
+        TextEditingValue {
+            text: String::from_utf16(&text).unwrap(),
+            selection_base: delta.selection_base,
+            selection_extent: delta.selection_extent,
+            selection_affinity: delta.selection_affinity.clone(),
+            selection_is_directional: delta.selection_is_directional,
+            composing_base: delta.composing_base,
+            composing_extent: delta.composing_extent,
+        }
+    }
+
+    fn assert_rebuilds(old: TextEditingValue, new: TextEditingValue) -> TextEditingDelta {
+        let delta = TextEditingDelta::between(&old, &new);
+        assert_eq!(delta.old_text, old.text);
+        assert_eq!(
+            serde_json::to_value(apply(&delta)).unwrap(),
+            serde_json::to_value(&new).unwrap()
+        );
+        delta
+    }
+
+    #[test]
+    fn insertion() {
+        let delta = assert_rebuilds(
+            value("hello world", (5, 5), (-1, -1)),
+            value("hello, world", (6, 6), (-1, -1)),
+        );
+        assert_eq!((delta.delta_start, delta.delta_end), (5, 5));
+        assert_eq!(delta.delta_text, ",");
+    }
+
+    #[test]
+    fn insertion_into_repeated_text() {
+        let delta = assert_rebuilds(
+            value("aa", (2, 2), (-1, -1)),
+            value("aaa", (3, 3), (-1, -1)),
+        );
+        assert_eq!((delta.delta_start, delta.delta_end), (2, 2));
+        assert_eq!(delta.delta_text, "a");
+    }
+
+    #[test]
+    fn deletion() {
+        let delta = assert_rebuilds(
+            value("hello world", (11, 11), (-1, -1)),
+            value("hello", (5, 5), (-1, -1)),
+        );
+        assert_eq!((delta.delta_start, delta.delta_end), (5, 11));
+        assert_eq!(delta.delta_text, "");
+    }
+
+    #[test]
+    fn replacement() {
+        let delta = assert_rebuilds(
+            value("hello world", (6, 11), (-1, -1)),
+            value("hello there", (11, 11), (-1, -1)),
+        );
+        assert_eq!((delta.delta_start, delta.delta_end), (6, 11));
+        assert_eq!(delta.delta_text, "there");
+    }
+
+    #[test]
+    fn selection_and_composing_only() {
+        let delta = assert_rebuilds(
+            value("hello", (5, 5), (-1, -1)),
+            value("hello", (0, 5), (0, 5)),
+        );
+        assert_eq!((delta.delta_start, delta.delta_end), (-1, -1));
+        assert_eq!(delta.delta_text, "");
+    }
+
+    #[test]
+    fn non_bmp_text() {
+        // 😀 is a surrogate pair, two UTF-16 code units.
+        let delta = assert_rebuilds(
+            value("a😀b", (3, 3), (-1, -1)),
+            value("a😀😀b", (5, 5), (-1, -1)),
+        );
+        assert_eq!((delta.delta_start, delta.delta_end), (3, 3));
+        assert_eq!(delta.delta_text, "😀");
+
+        let delta = assert_rebuilds(
+            value("👩‍👩‍👧 x", (8, 8), (-1, -1)),
+            value(" x", (0, 0), (-1, -1)),
+        );
+        assert_eq!((delta.delta_start, delta.delta_end), (0, 8));
+
+        let delta = assert_rebuilds(
+            value("x😀y", (1, 3), (-1, -1)),
+            value("x😁y", (3, 3), (1, 3)),
+        );
+        // The pairs share their high surrogate, but the delta still covers
+        // whole code points.
+        assert_eq!((delta.delta_start, delta.delta_end), (1, 3));
+        assert_eq!(delta.delta_text, "😁");
+    }
+}
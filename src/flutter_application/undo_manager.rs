@@ -0,0 +1,105 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::text_input::TextEditingValue;
+
+/// Typing within this time after the last keystroke is undone in one step.
+const TYPING_GROUP_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_UNDO_STEPS: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "method", content = "args")]
+pub(super) enum UndoManager {
+    /// The framework's undo history of the focused text field changed.
+    #[serde(rename = "UndoManager.setUndoState")]
+    SetUndoState(UndoState),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct UndoState {
+    pub(super) can_undo: bool,
+    pub(super) can_redo: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "method", content = "args")]
+pub(super) enum UndoManagerClient {
+    /// Asks the framework to undo or redo in its own undo history.
+    #[serde(rename = "UndoManagerClient.handlePlatformUndo")]
+    HandlePlatformUndo((UndoDirection,)),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub(super) enum UndoDirection {
+    Undo,
+    Redo,
+}
+
+/// Undo history of the text editing done by the embedder itself, used when
+/// the framework doesn't keep one for the text input client.
+#[derive(Default)]
+pub(super) struct UndoHistory {
+    undo_stack: Vec<TextEditingValue>,
+    redo_stack: Vec<TextEditingValue>,
+    /// When the current group of typed characters was last extended.
+    last_typed: Option<Instant>,
+}
+
+impl UndoHistory {
+    /// Records an edit from `previous` to `current`. Typed characters are
+    /// grouped with the preceding ones, anything else (deletion, cut,
+    /// paste…) is a step of its own.
+    pub(super) fn record(
+        &mut self,
+        previous: &TextEditingValue,
+        current: &TextEditingValue,
+        typing: bool,
+    ) {
+        if previous.text == current.text {
+            if previous.selection_base != current.selection_base
+                || previous.selection_extent != current.selection_extent
+            {
+                // Moving the caret starts a new group.
+                self.last_typed = None;
+            }
+            return;
+        }
+        let now = Instant::now();
+        let extends_group = typing
+            && self
+                .last_typed
+                .map(|last_typed| now - last_typed < TYPING_GROUP_TIMEOUT)
+                .unwrap_or(false);
+        if !extends_group {
+            self.undo_stack.push(previous.clone());
+            if self.undo_stack.len() > MAX_UNDO_STEPS {
+                self.undo_stack.remove(0);
+            }
+        }
+        self.last_typed = typing.then_some(now);
+        self.redo_stack.clear();
+    }
+
+    pub(super) fn undo(&mut self, current: &TextEditingValue) -> Option<TextEditingValue> {
+        let previous = self.undo_stack.pop()?;
+        self.redo_stack.push(current.clone());
+        self.last_typed = None;
+        Some(previous)
+    }
+
+    pub(super) fn redo(&mut self, current: &TextEditingValue) -> Option<TextEditingValue> {
+        let next = self.redo_stack.pop()?;
+        self.undo_stack.push(current.clone());
+        self.last_typed = None;
+        Some(next)
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.last_typed = None;
+    }
+}
@@ -0,0 +1,327 @@
+use serde::{Deserialize, Serialize};
+use winit::{
+    dpi::{LogicalPosition, LogicalSize},
+    event::MouseButton,
+    window::{ResizeDirection, Window, WindowLevel},
+};
+
+use super::{platform::Platform, text_input::Rect};
+
+#[derive(Default)]
+pub(super) struct WindowManager {
+    /// The regions of a window chrome drawn by the application, the last
+    /// one containing a point wins.
+    hit_regions: Vec<HitRegion>,
+}
+
+impl WindowManager {
+    /// Moves or resizes the window if a mouse button is pressed in a
+    /// region declared by the application for that. Returns whether the
+    /// press was consumed, in which case it must not be sent to Flutter.
+    pub(super) fn chrome_pressed(
+        &self,
+        window: &Window,
+        button: MouseButton,
+        position: (f64, f64),
+    ) -> bool {
+        let (x, y) = position;
+        let Some(region) = self.hit_regions.iter().rev().find(|region| {
+            x >= region.rect.x
+                && x < region.rect.x + region.rect.width
+                && y >= region.rect.y
+                && y < region.rect.y + region.rect.height
+        }) else {
+            return false;
+        };
+        let result = match (region.kind, button) {
+            (HitRegionKind::Caption, MouseButton::Left) => window.drag_window(),
+            (HitRegionKind::Caption, MouseButton::Right) => {
+                window.show_window_menu(LogicalPosition::new(x, y));
+                Ok(())
+            }
+            (HitRegionKind::Resize { edge }, MouseButton::Left) => {
+                window.drag_resize_window(edge.into())
+            }
+            _ => return false,
+        };
+        if let Err(err) = result {
+            log::error!("Failed moving or resizing the window: {err}");
+            return false;
+        }
+        true
+    }
+
+    pub(super) fn handle_message(
+        &mut self,
+        message: WindowManagerMessage,
+        window: &Window,
+    ) -> Option<Vec<u8>> {
+        log::debug!("Window manager message: {message:?}");
+        let scale_factor = window.scale_factor();
+        let result = match message {
+            WindowManagerMessage::SetSize(size) => {
+                let _ = window.request_inner_size(LogicalSize::from(size));
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::GetSize => {
+                let size = window.inner_size().to_logical::<f64>(scale_factor);
+                serde_json::to_value(Size::from(size)).unwrap()
+            }
+            WindowManagerMessage::SetPosition(position) => {
+                window.set_outer_position(LogicalPosition::from(position));
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::GetPosition => match window.outer_position() {
+                Ok(position) => {
+                    let position = position.to_logical::<f64>(scale_factor);
+                    serde_json::to_value(Position::from(position)).unwrap()
+                }
+                // e.g. on Wayland, where windows don't know their position.
+                Err(err) => return Some(Platform::error("Unsupported", err)),
+            },
+            WindowManagerMessage::SetMinSize(size) => {
+                window.set_min_inner_size(size.map(LogicalSize::from));
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::SetMaxSize(size) => {
+                window.set_max_inner_size(size.map(LogicalSize::from));
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::SetResizable(resizable) => {
+                window.set_resizable(resizable);
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::SetDecorations(decorations) => {
+                window.set_decorations(decorations);
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::SetAlwaysOnTop(always_on_top) => {
+                window.set_window_level(if always_on_top {
+                    WindowLevel::AlwaysOnTop
+                } else {
+                    WindowLevel::Normal
+                });
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::Minimize => {
+                window.set_minimized(true);
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::Maximize => {
+                window.set_maximized(true);
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::Restore => {
+                window.set_minimized(false);
+                window.set_maximized(false);
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::Focus => {
+                window.focus_window();
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::Show => {
+                window.set_visible(true);
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::Hide => {
+                window.set_visible(false);
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::StartDrag => match window.drag_window() {
+                Ok(()) => serde_json::Value::Null,
+                Err(err) => return Some(Platform::error("Unsupported", err)),
+            },
+            WindowManagerMessage::StartResize(edge) => match window.drag_resize_window(edge.into())
+            {
+                Ok(()) => serde_json::Value::Null,
+                Err(err) => return Some(Platform::error("Unsupported", err)),
+            },
+            WindowManagerMessage::ShowMenu(position) => {
+                window.show_window_menu(LogicalPosition::from(position));
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::SetHitRegions(hit_regions) => {
+                self.hit_regions = hit_regions;
+                serde_json::Value::Null
+            }
+            WindowManagerMessage::IsMinimized => window.is_minimized().into(),
+            WindowManagerMessage::IsMaximized => window.is_maximized().into(),
+            WindowManagerMessage::IsVisible => window.is_visible().into(),
+            WindowManagerMessage::IsFocused => window.has_focus().into(),
+            message @ (WindowManagerMessage::Moved(_) | WindowManagerMessage::Resized(_)) => {
+                log::warn!("Outgoing window message received: {message:?}");
+                return None;
+            }
+        };
+        Some(Platform::success(result))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "method", content = "args")]
+pub(super) enum WindowManagerMessage {
+    /// Requests a new size of the window's content area, in logical pixels.
+    /// The window manager might not honor it.
+    #[serde(rename = "Window.setSize")]
+    SetSize(Size),
+    #[serde(rename = "Window.getSize")]
+    GetSize,
+    /// Moves the window, including its decorations, in logical pixels.
+    #[serde(rename = "Window.setPosition")]
+    SetPosition(Position),
+    /// Returns the position of the window, or an error if the platform
+    /// doesn't tell.
+    #[serde(rename = "Window.getPosition")]
+    GetPosition,
+    /// `null` removes the constraint.
+    #[serde(rename = "Window.setMinSize")]
+    SetMinSize(Option<Size>),
+    /// `null` removes the constraint.
+    #[serde(rename = "Window.setMaxSize")]
+    SetMaxSize(Option<Size>),
+    #[serde(rename = "Window.setResizable")]
+    SetResizable(bool),
+    #[serde(rename = "Window.setDecorations")]
+    SetDecorations(bool),
+    #[serde(rename = "Window.setAlwaysOnTop")]
+    SetAlwaysOnTop(bool),
+    #[serde(rename = "Window.minimize")]
+    Minimize,
+    #[serde(rename = "Window.maximize")]
+    Maximize,
+    /// Undoes minimizing and maximizing.
+    #[serde(rename = "Window.restore")]
+    Restore,
+    #[serde(rename = "Window.focus")]
+    Focus,
+    #[serde(rename = "Window.show")]
+    Show,
+    #[serde(rename = "Window.hide")]
+    Hide,
+    /// Returns `null` if the platform doesn't tell.
+    #[serde(rename = "Window.isMinimized")]
+    IsMinimized,
+    /// Returns `null` if the platform doesn't tell.
+    #[serde(rename = "Window.isMaximized")]
+    IsMaximized,
+    /// Returns `null` if the platform doesn't tell.
+    #[serde(rename = "Window.isVisible")]
+    IsVisible,
+    #[serde(rename = "Window.isFocused")]
+    IsFocused,
+    /// Moves the window with the mouse. Only works while a mouse button is
+    /// pressed, so it should be sent on pointer down.
+    #[serde(rename = "Window.startDrag")]
+    StartDrag,
+    /// Resizes the window at the given edge with the mouse. Only works
+    /// while a mouse button is pressed, so it should be sent on pointer down.
+    #[serde(rename = "Window.startResize")]
+    StartResize(ResizeEdge),
+    /// Shows the window menu of the system at the given position.
+    #[serde(rename = "Window.showMenu")]
+    ShowMenu(Position),
+    /// Declares the parts of the window that act like a title bar or a
+    /// resize border, handled without waiting for Dart. Replaces any earlier
+    /// regions.
+    #[serde(rename = "Window.setHitRegions")]
+    SetHitRegions(Vec<HitRegion>),
+    /// Outgoing. The window was moved.
+    #[serde(rename = "Window.moved")]
+    Moved(Position),
+    /// Outgoing. The window's content area was resized.
+    #[serde(rename = "Window.resized")]
+    Resized(Size),
+}
+
+/// A size in logical pixels.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub(super) struct Size {
+    pub(super) width: f64,
+    pub(super) height: f64,
+}
+
+impl From<Size> for LogicalSize<f64> {
+    fn from(size: Size) -> Self {
+        LogicalSize::new(size.width, size.height)
+    }
+}
+
+impl From<LogicalSize<f64>> for Size {
+    fn from(size: LogicalSize<f64>) -> Self {
+        Self {
+            width: size.width,
+            height: size.height,
+        }
+    }
+}
+
+/// A position in logical pixels.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub(super) struct Position {
+    pub(super) x: f64,
+    pub(super) y: f64,
+}
+
+impl From<Position> for LogicalPosition<f64> {
+    fn from(position: Position) -> Self {
+        LogicalPosition::new(position.x, position.y)
+    }
+}
+
+impl From<LogicalPosition<f64>> for Position {
+    fn from(position: LogicalPosition<f64>) -> Self {
+        Self {
+            x: position.x,
+            y: position.y,
+        }
+    }
+}
+
+/// A region of the window in logical pixels.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub(super) struct HitRegion {
+    rect: Rect,
+    #[serde(flatten)]
+    kind: HitRegionKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(super) enum HitRegionKind {
+    /// Handled by Flutter, e.g. a button in the title bar.
+    Client,
+    /// Moves the window when dragged, shows the window menu on right
+    /// click.
+    Caption,
+    /// Resizes the window when dragged.
+    Resize { edge: ResizeEdge },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub(super) enum ResizeEdge {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl From<ResizeEdge> for ResizeDirection {
+    fn from(edge: ResizeEdge) -> Self {
+        match edge {
+            ResizeEdge::North => ResizeDirection::North,
+            ResizeEdge::NorthEast => ResizeDirection::NorthEast,
+            ResizeEdge::East => ResizeDirection::East,
+            ResizeEdge::SouthEast => ResizeDirection::SouthEast,
+            ResizeEdge::South => ResizeDirection::South,
+            ResizeEdge::SouthWest => ResizeDirection::SouthWest,
+            ResizeEdge::West => ResizeDirection::West,
+            ResizeEdge::NorthWest => ResizeDirection::NorthWest,
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use winit::keyboard::{Key, KeyLocation, NamedKey};
+
+/// Translates a logical key to the GDK keyval the GTK embedder would report
+/// for it, as used by `RawKeyEventDataLinux` with the GTK key helper.
+pub fn translate_gtk_keyval(key: &Key, location: KeyLocation) -> Option<u64> {
+    Some(match key {
+        Key::Character(c) => {
+            let c = c.chars().next()?;
+            if location == KeyLocation::Numpad {
+                match c {
+                    '0'..='9' => return Some(0xffb0 + (c as u64 - '0' as u64)),
+                    '*' => return Some(0xffaa),
+                    '+' => return Some(0xffab),
+                    '-' => return Some(0xffad),
+                    '.' | ',' => return Some(0xffae),
+                    '/' => return Some(0xffaf),
+                    '=' => return Some(0xffbd),
+                    _ => {}
+                }
+            }
+            match c as u64 {
+                // Latin-1 keyvals are identical to their code points, all
+                // others are offset.
+                code_point @ (0x20..=0x7e | 0xa0..=0xff) => code_point,
+                code_point => 0x01000000 | code_point,
+            }
+        }
+        Key::Named(key) => match key {
+            NamedKey::Space => 0x0020,
+            NamedKey::Backspace => 0xff08,
+            NamedKey::Tab => 0xff09,
+            NamedKey::Clear => 0xff0b,
+            NamedKey::Enter if location == KeyLocation::Numpad => 0xff8d,
+            NamedKey::Enter => 0xff0d,
+            NamedKey::Pause => 0xff13,
+            NamedKey::ScrollLock => 0xff14,
+            NamedKey::Escape => 0xff1b,
+            NamedKey::Home => 0xff50,
+            NamedKey::ArrowLeft => 0xff51,
+            NamedKey::ArrowUp => 0xff52,
+            NamedKey::ArrowRight => 0xff53,
+            NamedKey::ArrowDown => 0xff54,
+            NamedKey::PageUp => 0xff55,
+            NamedKey::PageDown => 0xff56,
+            NamedKey::End => 0xff57,
+            NamedKey::Select => 0xff60,
+            NamedKey::PrintScreen => 0xff61,
+            NamedKey::Execute => 0xff62,
+            NamedKey::Insert => 0xff63,
+            NamedKey::Undo => 0xff65,
+            NamedKey::Redo => 0xff66,
+            NamedKey::ContextMenu => 0xff67,
+            NamedKey::Find => 0xff68,
+            NamedKey::Cancel => 0xff69,
+            NamedKey::Help => 0xff6a,
+            NamedKey::NumLock => 0xff7f,
+            NamedKey::F1 => 0xffbe,
+            NamedKey::F2 => 0xffbf,
+            NamedKey::F3 => 0xffc0,
+            NamedKey::F4 => 0xffc1,
+            NamedKey::F5 => 0xffc2,
+            NamedKey::F6 => 0xffc3,
+            NamedKey::F7 => 0xffc4,
+            NamedKey::F8 => 0xffc5,
+            NamedKey::F9 => 0xffc6,
+            NamedKey::F10 => 0xffc7,
+            NamedKey::F11 => 0xffc8,
+            NamedKey::F12 => 0xffc9,
+            NamedKey::F13 => 0xffca,
+            NamedKey::F14 => 0xffcb,
+            NamedKey::F15 => 0xffcc,
+            NamedKey::F16 => 0xffcd,
+            NamedKey::F17 => 0xffce,
+            NamedKey::F18 => 0xffcf,
+            NamedKey::F19 => 0xffd0,
+            NamedKey::F20 => 0xffd1,
+            NamedKey::F21 => 0xffd2,
+            NamedKey::F22 => 0xffd3,
+            NamedKey::F23 => 0xffd4,
+            NamedKey::F24 => 0xffd5,
+            NamedKey::Shift if location == KeyLocation::Right => 0xffe2,
+            NamedKey::Shift => 0xffe1,
+            NamedKey::Control if location == KeyLocation::Right => 0xffe4,
+            NamedKey::Control => 0xffe3,
+            NamedKey::CapsLock => 0xffe5,
+            NamedKey::Meta if location == KeyLocation::Right => 0xffe8,
+            NamedKey::Meta => 0xffe7,
+            NamedKey::Alt if location == KeyLocation::Right => 0xffea,
+            NamedKey::Alt => 0xffe9,
+            NamedKey::Super if location == KeyLocation::Right => 0xffec,
+            NamedKey::Super => 0xffeb,
+            NamedKey::Hyper if location == KeyLocation::Right => 0xffee,
+            NamedKey::Hyper => 0xffed,
+            NamedKey::AltGraph => 0xfe03,
+            NamedKey::Delete => 0xffff,
+            NamedKey::AudioVolumeDown => 0x1008ff11,
+            NamedKey::AudioVolumeMute => 0x1008ff12,
+            NamedKey::AudioVolumeUp => 0x1008ff13,
+            NamedKey::MediaPlayPause => 0x1008ff14,
+            NamedKey::MediaStop => 0x1008ff15,
+            NamedKey::MediaTrackPrevious => 0x1008ff16,
+            NamedKey::MediaTrackNext => 0x1008ff17,
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
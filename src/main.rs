@@ -16,9 +16,13 @@ use winit::{
 };
 
 mod flutter_application;
-use flutter_application::{FlutterApplication, FlutterApplicationCallback};
+use flutter_application::{
+    feedback::{command_haptic_handler, AudioSoundBackend, SystemSoundType},
+    FlutterApplication, FlutterApplicationCallback, ScrollSettings, UserSettings,
+};
 
 mod action_key;
+mod keyboard_gtk_keyval_map;
 mod keyboard_logical_key_map;
 mod keyboard_physical_key_map;
 
@@ -41,6 +45,57 @@ struct Args {
     /// `flutter_tester --help` using the test binary included in the
     /// Flutter tools.
     pub flutter_flags: Vec<String>,
+    /// How many logical pixels a single line of mouse wheel scrolling
+    /// moves. The desktop's scroll speed setting isn't read, this is the only
+    /// way to change it.
+    #[clap(long, default_value_t = 10.0)]
+    pub pixels_per_line: f64,
+    /// Inverts the mouse wheel's scroll direction. The desktop's natural
+    /// scrolling setting isn't read for wheels, trackpads already follow it.
+    #[clap(long)]
+    pub natural_scrolling: bool,
+    /// Don't put selected text into the PRIMARY selection and don't paste it
+    /// on middle click.
+    #[clap(long)]
+    pub no_primary_selection: bool,
+    /// Open the window without the title bar and borders of the system,
+    /// for applications that draw their own.
+    #[clap(long)]
+    pub no_decorations: bool,
+    /// Scales all text by this factor.
+    #[clap(long, default_value_t = 1.0)]
+    pub text_scale_factor: f64,
+    /// Show times in the 24-hour format regardless of the locale.
+    #[clap(long)]
+    pub always_use_24_hour_format: bool,
+    /// Ask the application for a high contrast UI.
+    #[clap(long)]
+    pub high_contrast: bool,
+    /// Ask the application to reduce or skip animations.
+    #[clap(long)]
+    pub reduce_motion: bool,
+    /// Ask the application to use bold text.
+    #[clap(long)]
+    pub bold_text: bool,
+    /// The preferred locales as a colon separated list like `de_AT:en_US`.
+    /// Defaults to the locales from `LANGUAGE`, `LC_ALL` and `LANG`.
+    #[clap(long)]
+    pub locales: Option<String>,
+    /// Don't play the click and alert sounds.
+    #[clap(long)]
+    pub no_sound: bool,
+    /// A WAV file played for `SystemSoundType.click` instead of the bundled
+    /// sound.
+    #[clap(long)]
+    pub click_sound: Option<PathBuf>,
+    /// A WAV file played for `SystemSoundType.alert` instead of the bundled
+    /// sound.
+    #[clap(long)]
+    pub alert_sound: Option<PathBuf>,
+    /// A program that is run for haptic feedback with the feedback type,
+    /// e.g. `mediumImpact`, as its argument.
+    #[clap(long)]
+    pub haptic_command: Option<String>,
 }
 
 fn main() -> Result<(), std::io::Error> {
@@ -52,7 +107,9 @@ fn main() -> Result<(), std::io::Error> {
             .build()
             .unwrap();
 
-    let window_attr = WindowAttributes::default().with_title("Flutter Embedder");
+    let window_attr = WindowAttributes::default()
+        .with_title("Flutter Embedder")
+        .with_decorations(!args.no_decorations);
 
     #[cfg(any(x11_platform, wayland_platform))]
     if let Some(token) = event_loop.read_token_from_env() {
@@ -169,13 +226,52 @@ fn main() -> Result<(), std::io::Error> {
             },
         );
 
+        app.set_scroll_settings(ScrollSettings {
+            pixels_per_line: args.pixels_per_line,
+            natural_scrolling: args.natural_scrolling,
+        });
+        app.set_primary_selection_enabled(!args.no_primary_selection);
+        // Without a backend, no sounds are played.
+        if !args.no_sound {
+            match AudioSoundBackend::new() {
+                Ok(mut backend) => {
+                    for (sound, path) in [
+                        (SystemSoundType::Click, &args.click_sound),
+                        (SystemSoundType::Alert, &args.alert_sound),
+                    ] {
+                        let Some(path) = path else {
+                            continue;
+                        };
+                        match std::fs::read(path) {
+                            Ok(sound_file) => backend.set_sound(sound, sound_file),
+                            Err(err) => log::error!("Failed to read {path:?}: {err}"),
+                        }
+                    }
+                    app.set_sound_backend(backend);
+                }
+                Err(err) => log::warn!("No audio output, system sounds are disabled: {err}"),
+            }
+        }
+        if let Some(haptic_command) = args.haptic_command {
+            app.set_haptic_handler(command_haptic_handler(haptic_command));
+        }
+
         log::info!("Created Flutter App, and running it...");
 
         app.run();
 
+        app.set_locales(args.locales.as_deref());
+        app.set_user_settings(UserSettings {
+            text_scale_factor: args.text_scale_factor,
+            always_use_24_hour_format: args.always_use_24_hour_format,
+            high_contrast: args.high_contrast,
+            reduce_motion: args.reduce_motion,
+            bold_text: args.bold_text,
+        });
+
         // Trigger a FlutterEngineSendWindowMetricsEvent to communicate the initial
         // size of the window.
-        metrics_changed(&app, &window);
+        metrics_changed(&mut app, &window);
 
         let _ = event_loop.run(|event, active_event_loop| {
             // let _ = &adapter;
@@ -195,13 +291,18 @@ fn main() -> Result<(), std::io::Error> {
                 // }
                 Event::WindowEvent { event, .. } => match event {
                     WindowEvent::CloseRequested => {
-                        // *control_flow = ControlFlow::Exit;
-                        active_event_loop.exit();
+                        app.request_exit();
+                    }
+                    WindowEvent::Moved(position) => {
+                        metrics_changed(&mut app, &window);
+                        app.window_moved(position);
                     }
-                    WindowEvent::Moved(_)
-                    | WindowEvent::Resized(_)
-                    | WindowEvent::ScaleFactorChanged { .. } => {
-                        metrics_changed(&app, &window);
+                    WindowEvent::Resized(size) => {
+                        metrics_changed(&mut app, &window);
+                        app.window_resized(size);
+                    }
+                    WindowEvent::ScaleFactorChanged { .. } => {
+                        metrics_changed(&mut app, &window);
                     }
                     WindowEvent::MouseInput {
                         device_id,
@@ -232,6 +333,30 @@ fn main() -> Result<(), std::io::Error> {
                     } => {
                         app.mouse_wheel(device_id, delta, phase);
                     }
+                    WindowEvent::PanGesture {
+                        device_id,
+                        delta,
+                        phase,
+                    } => {
+                        app.pan_gesture(device_id, delta, phase);
+                    }
+                    WindowEvent::PinchGesture {
+                        device_id,
+                        delta,
+                        phase,
+                    } => {
+                        app.pinch_gesture(device_id, delta, phase);
+                    }
+                    WindowEvent::RotationGesture {
+                        device_id,
+                        delta,
+                        phase,
+                    } => {
+                        app.rotation_gesture(device_id, delta, phase);
+                    }
+                    WindowEvent::Touch(touch) => {
+                        app.touch(touch);
+                    }
                     WindowEvent::ModifiersChanged(state) => {
                         app.modifiers_changed(state.state());
                     }
@@ -242,15 +367,42 @@ fn main() -> Result<(), std::io::Error> {
                     } => {
                         app.key_event(device_id, event, is_synthetic);
                     }
+                    WindowEvent::Ime(ime) => {
+                        app.ime(ime);
+                    }
+                    WindowEvent::HoveredFile(path) => {
+                        app.file_hovered(path);
+                    }
+                    WindowEvent::DroppedFile(path) => {
+                        app.file_dropped(path);
+                    }
+                    WindowEvent::HoveredFileCancelled => {
+                        app.file_hover_cancelled();
+                    }
                     WindowEvent::Focused(focused) => {
                         app.focused(focused);
                     }
+                    WindowEvent::ThemeChanged(theme) => {
+                        app.theme_changed(theme);
+                    }
+                    WindowEvent::Occluded(occluded) => {
+                        app.occluded(occluded);
+                    }
 
                     WindowEvent::RedrawRequested => {
                         app.schedule_frame();
                     }
                     _ => {}
                 },
+                Event::Suspended => {
+                    app.suspended(true);
+                }
+                Event::Resumed => {
+                    app.suspended(false);
+                }
+                Event::LoopExiting => {
+                    app.detach();
+                }
                 _ => {}
             }
         });
@@ -258,7 +410,7 @@ fn main() -> Result<(), std::io::Error> {
     Ok(())
 }
 
-fn metrics_changed(application: &FlutterApplication, window: &Window) {
+fn metrics_changed(application: &mut FlutterApplication, window: &Window) {
     log::info!("Metrics Changed");
 
     let size = window.inner_size();